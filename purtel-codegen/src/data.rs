@@ -1,55 +1,466 @@
 //! Codegen. Definition of parsing targets.
 
 use darling::FromMeta;
+use proc_macro2::{Ident, Span};
+use std::ops::Range;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Error, Expr, ExprField, ExprPath, LitInt, Member, Path, Token, bracketed, parenthesized, parse_quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use purtel_core::{PurtelParamUsage, PurtelParamUsageKind};
+use crate::infer::ParamAliases;
 
 /// Describes the data that directly corresponds to the attributes of the
-/// `purtel_task`-proc-macro. This sstruct is needed as parsing target for
-/// the crate "darling" which works together with "syn". The semantically
-/// meaning is the description what parameters a tasks uses in what mode
-/// (read or write).
-// FromMeta is from "darling" crate; implements "from_list"-factory-method
-#[derive(Debug, FromMeta)]
-pub struct PurtelTaskAttributes {
-    /// value inside `write = "param1, param2, ..."`
+/// `purtel_tasks`-proc-macro (or the inner attribute `#![purtel_tasks(...)]`
+/// of `purtel_block!`). Parsing target for the crate "darling".
+#[derive(Debug, Default, FromMeta)]
+pub struct PurtelTasksAttributes {
+    /// `#[purtel_tasks(run)]`: the block becomes an expression that builds, plans and
+    /// runs the executor and evaluates to the execution result
+    #[darling(default)]
+    pub run: bool,
+    /// `#[purtel_tasks(name = "tasks")]`: a single binding `tasks` (a `PurtelTaskGraph`)
+    /// instead of `closures`, `param_usages` and `PURTEL_SCHEDULE`
+    #[darling(default)]
+    pub name: Option<Ident>,
+    /// `#[purtel_tasks(closures = "tasks", param_usages = "usages", schedule = "SCHEDULE")]`:
+    /// other names for the bindings `closures`, `param_usages` and `PURTEL_SCHEDULE`
     #[darling(default)]
-    write: Option<String>,
-    /// value inside `read = "param1, param2, ..."`
+    closures: Option<Ident>,
     #[darling(default)]
-    read: Option<String>,
+    param_usages: Option<Ident>,
+    #[darling(default)]
+    schedule: Option<Ident>,
+    /// `#[purtel_tasks(crate = "purtel_core")]`: path to the crate with the purtel types;
+    /// needed if the crate is renamed or if only purtel-core is a dependency
+    #[darling(default, rename = "crate")]
+    krate: Option<Path>,
+}
+
+impl PurtelTasksAttributes {
+
+    /// Path to the crate with the purtel types. Default is `purtel`.
+    pub fn krate(&self) -> Path {
+        self.krate.clone().unwrap_or_else(|| parse_quote!(purtel))
+    }
+
+    /// Name of the generated vector of tasks. Default is `closures`.
+    pub fn closures(&self) -> Ident {
+        self.closures.clone().unwrap_or_else(|| Ident::new("closures", Span::call_site()))
+    }
+
+    /// Name of the generated vector of parameter usages. Default is `param_usages`.
+    pub fn param_usages(&self) -> Ident {
+        self.param_usages.clone().unwrap_or_else(|| Ident::new("param_usages", Span::call_site()))
+    }
+
+    /// Name of the generated constant with the schedule. Default is `PURTEL_SCHEDULE`.
+    pub fn schedule(&self) -> Ident {
+        self.schedule.clone().unwrap_or_else(|| Ident::new("PURTEL_SCHEDULE", Span::call_site()))
+    }
+
+    /// The first of the names of the separate bindings that is set, if any.
+    pub fn binding_name(&self) -> Option<&Ident> {
+        self.closures.as_ref().or(self.param_usages.as_ref()).or(self.schedule.as_ref())
+    }
+}
+
+/// Describes the data that directly corresponds to the attributes of the
+/// `purtel_task`-proc-macro: `#[purtel_task(read(...), write(...), reduce(...), exclusive(...), after(...), guards)]`.
+/// The semantically meaning is the description what parameters a tasks uses
+/// in what mode (read or write). Parsed with "syn" instead of "darling"
+/// because parameters can be fields like `state.positions`, which aren't
+/// valid in a meta list.
+#[derive(Debug, Default)]
+pub struct PurtelTaskAttributes {
+    /// parameters inside `write(param1, param2, ...)`
+    write: Vec<PurtelParam>,
+    /// parameters inside `read(param1, param2, ...)`
+    read: Vec<PurtelParam>,
+    /// parameters inside `reduce(param1, param2, ...)`; commutative updates
+    /// of a `PurtelReduction`
+    reduce: Vec<PurtelParam>,
+    /// parameters inside `exclusive(param1, param2, ...)`; exclusive access in any order
+    exclusive: Vec<PurtelParam>,
+    /// tasks inside `after(task1, task2, ...)` (or `after = task1`); explicit dependencies
+    after: Vec<Ident>,
+    /// `guards`: clone the declared parameters into the task and
+    /// bind their guards at the beginning of the closure body
+    guards: bool,
 }
 
 impl PurtelTaskAttributes {
 
-    /// Getter. Maps property `write` of `PurtelTaskAttributes` from a
-    /// comma-separated string to a vector of strings.
-    pub fn write_params(&self) -> Vec<String> {
-        self.write.as_ref()
-            .map(|s| s.clone())
-            .map(|s| s.split(",").into_iter()
-                .map(|s| s.to_owned())
-                .collect::<Vec<String>>()
-            )
-            .unwrap_or(vec![]).into_iter()
-            .map(|s| s.trim().to_owned())
-            .collect::<Vec<String>>()
-    }
-
-    /// Getter. Maps property `read` of `PurtelTaskAttributes`  from a
-    /// comma-separated string to a vector of strings.
-    pub fn read_params(&self) -> Vec<String> {
-        self.read.as_ref()
-            .map(|s| s.clone())
-            .map(|s| s.split(",").into_iter()
-                .map(|s| s.to_owned())
-                .collect::<Vec<String>>()
-            )
-            .unwrap_or(vec![]).into_iter()
-            .map(|s| s.trim().to_owned())
-            // we filter out params in read that are already in write
-            // in case a developer added the same parameter to both by accident
-            .filter(|s| self.write.as_ref()
-                .map(|rs| !rs.contains(s)).unwrap_or(true)
-            )
-            .collect::<Vec<String>>()
+    /// Parses the arguments of the attribute; just `#[purtel_task]` has none.
+    pub fn from_attr(attr: &Attribute) -> syn::Result<Self> {
+        if attr.tokens.is_empty() {
+            return Ok(Self::default());
+        }
+        attr.parse_args()
+    }
+
+    /// Getter. Maps property `write` of `PurtelTaskAttributes` to the
+    /// parameter usages; clones are resolved with `aliases`. Duplicates are errors.
+    pub fn write_params(&self, aliases: &ParamAliases) -> syn::Result<Vec<PurtelParamUsage>> {
+        param_usages(&self.write, PurtelParamUsageKind::WRITE, aliases)
+    }
+
+    /// Getter. Maps property `read` of `PurtelTaskAttributes` to the
+    /// parameter usages. Duplicates and parameters that are also
+    /// in `write` are errors.
+    pub fn read_params(&self, aliases: &ParamAliases) -> syn::Result<Vec<PurtelParamUsage>> {
+        let write_params = self.write_params(aliases)?;
+        let read_params = param_usages(&self.read, PurtelParamUsageKind::READ, aliases)?;
+        check_conflicts(&self.read, &read_params, &write_params, "write already implies read")?;
+        Ok(read_params)
+    }
+
+    /// Getter. Maps property `reduce` of `PurtelTaskAttributes` to the
+    /// parameter usages. Duplicates and parameters that are also
+    /// in `write` or `read` are errors.
+    pub fn reduce_params(&self, aliases: &ParamAliases) -> syn::Result<Vec<PurtelParamUsage>> {
+        let write_params = self.write_params(aliases)?;
+        let read_params = self.read_params(aliases)?;
+        let reduce_params = param_usages(&self.reduce, PurtelParamUsageKind::REDUCE, aliases)?;
+        check_conflicts(&self.reduce, &reduce_params, &write_params, "write already implies reduce")?;
+        check_conflicts(&self.reduce, &reduce_params, &read_params, "declare it as write instead")?;
+        Ok(reduce_params)
+    }
+
+    /// Getter. Maps property `exclusive` of `PurtelTaskAttributes` to the
+    /// parameter usages. Duplicates and parameters that are also
+    /// in `write`, `read` or `reduce` are errors.
+    pub fn exclusive_params(&self, aliases: &ParamAliases) -> syn::Result<Vec<PurtelParamUsage>> {
+        let exclusive_params = param_usages(&self.exclusive, PurtelParamUsageKind::EXCLUSIVE, aliases)?;
+        let hint = "exclusive already implies every access";
+        check_conflicts(&self.exclusive, &exclusive_params, &self.write_params(aliases)?, hint)?;
+        check_conflicts(&self.exclusive, &exclusive_params, &self.read_params(aliases)?, hint)?;
+        check_conflicts(&self.exclusive, &exclusive_params, &self.reduce_params(aliases)?, hint)?;
+        Ok(exclusive_params)
+    }
+
+    pub fn write_exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.write.iter().map(|param| &param.expr)
+    }
+
+    pub fn read_exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.read.iter().map(|param| &param.expr)
+    }
+
+    pub fn reduce_exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.reduce.iter().map(|param| &param.expr)
+    }
+
+    pub fn exclusive_exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.exclusive.iter().map(|param| &param.expr)
+    }
+
+    /// The first declared parameter with an index or a range, if any.
+    pub fn ranged_expr(&self) -> Option<&Expr> {
+        self.write.iter()
+            .chain(&self.read)
+            .chain(&self.reduce)
+            .chain(&self.exclusive)
+            .find(|param| param.range.is_some())
+            .map(|param| &param.expr)
+    }
+
+    /// Names of the tasks this task must run after.
+    pub fn after(&self) -> &[Ident] {
+        &self.after
+    }
+
+    pub fn guards(&self) -> bool {
+        self.guards
+    }
+
+    /// Returns all explicitly declared parameters. Used to check that they
+    /// exist and are shared handles.
+    pub fn declared_exprs(&self) -> Vec<Expr> {
+        self.write_exprs()
+            .chain(self.read_exprs())
+            .chain(self.reduce_exprs())
+            .chain(self.exclusive_exprs())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Parse for PurtelTaskAttributes {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        let mut keys: Vec<String> = vec![];
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if keys.contains(&key.to_string()) {
+                return Err(Error::new_spanned(&key, format!("duplicate `{}`", key)));
+            }
+            keys.push(key.to_string());
+            match key.to_string().as_str() {
+                "read" | "write" | "reduce" | "exclusive" => {
+                    // the old syntax `read = "data1, data2"`
+                    if input.peek(Token![=]) {
+                        return Err(Error::new_spanned(
+                            &key,
+                            format!("expected a list of parameters like `{}(data1, data2)`", key),
+                        ));
+                    }
+                    let content;
+                    parenthesized!(content in input);
+                    let params = Punctuated::<PurtelParam, Token![,]>::parse_terminated(&content)?;
+                    if params.is_empty() {
+                        return Err(Error::new_spanned(&key, format!("expected at least one parameter in `{}(...)`", key)));
+                    }
+                    let params = params.into_iter().collect();
+                    match key.to_string().as_str() {
+                        "read" => attrs.read = params,
+                        "write" => attrs.write = params,
+                        "reduce" => attrs.reduce = params,
+                        _ => attrs.exclusive = params,
+                    }
+                },
+                "after" => {
+                    attrs.after = if input.peek(Token![=]) {
+                        input.parse::<Token![=]>()?;
+                        vec![input.parse()?]
+                    } else {
+                        let content;
+                        parenthesized!(content in input);
+                        Punctuated::<Ident, Token![,]>::parse_terminated(&content)?.into_iter().collect()
+                    };
+                },
+                "guards" => attrs.guards = true,
+                _ => return Err(Error::new_spanned(
+                    &key,
+                    format!("unknown `{}`; expected `read(...)`, `write(...)`, `reduce(...)`, `exclusive(...)`, `after(...)` or `guards`", key),
+                )),
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+/// A declared parameter: a variable like `data1`, a path like `state::DATA`
+/// or a field like `state.positions`. Optionally followed by an index (`data1[3]`)
+/// or a half-open range (`data1[0..1024]`) of integer literals; the schedule is
+/// calculated at compile time, hence the bounds must be known.
+#[derive(Debug)]
+pub struct PurtelParam {
+    /// the shared handle, without index or range
+    expr: Expr,
+    range: Option<Range<usize>>,
+}
+
+impl Parse for PurtelParam {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.call(Path::parse_mod_style)?;
+        let mut expr = Expr::Path(ExprPath { attrs: vec![], qself: None, path });
+        while input.peek(Token![.]) {
+            expr = Expr::Field(ExprField {
+                attrs: vec![],
+                base: Box::new(expr),
+                dot_token: input.parse()?,
+                member: Member::Named(input.parse()?),
+            });
+        }
+        let range = if input.peek(syn::token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            Some(parse_range(&content)?)
+        } else {
+            None
+        };
+        Ok(Self { expr, range })
+    }
+}
+
+/// Parses `3` (the range `3..4`) or `0..1024`.
+fn parse_range(input: ParseStream) -> syn::Result<Range<usize>> {
+    let expected = "expected an index like `data1[3]` or a range like `data1[0..1024]` of integer literals";
+    let start: LitInt = input.parse().map_err(|err| Error::new(err.span(), expected))?;
+    let start_value = start.base10_parse::<usize>()?;
+    if input.is_empty() {
+        return Ok(start_value..start_value + 1);
+    }
+    input.parse::<Token![..]>().map_err(|err| Error::new(err.span(), expected))?;
+    let end: LitInt = input.parse().map_err(|err| Error::new(err.span(), expected))?;
+    let end_value = end.base10_parse::<usize>()?;
+    if !input.is_empty() {
+        return Err(input.error(expected));
+    }
+    if end_value < start_value {
+        return Err(Error::new_spanned(&end, "the end of the range must not be smaller than its start"));
+    }
+    Ok(start_value..end_value)
+}
+
+/// Returns the parameter identifier of a variable (`data1`), path (`state::DATA`) or
+/// field (`state.positions`); `None` for all other expressions.
+pub fn param_identifier(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(path) if path.qself.is_none() => {
+            let segments = &path.path.segments;
+            if segments.iter().any(|segment| !segment.arguments.is_empty()) {
+                return None;
+            }
+            Some(segments.iter().map(|segment| segment.ident.to_string()).collect::<Vec<_>>().join("::"))
+        },
+        Expr::Field(field) => match &field.member {
+            Member::Named(member) => param_identifier(&field.base).map(|base| format!("{}.{}", base, member)),
+            Member::Unnamed(_) => None,
+        },
+        Expr::Paren(paren) => param_identifier(&paren.expr),
+        _ => None,
+    }
+}
+
+/// Maps the parameters of `read(...)`/`write(...)` to parameter usages. Clones like
+/// `data1_t` are resolved to the parameter (`data1`), the same way as for the inferred
+/// usage. Duplicates are errors, spanned at the parameter; disjoint ranges of the same
+/// parameter are fine.
+fn param_usages(params: &[PurtelParam],
+                kind: PurtelParamUsageKind,
+                aliases: &ParamAliases) -> syn::Result<Vec<PurtelParamUsage>> {
+    let mut usages: Vec<PurtelParamUsage> = vec![];
+    for param in params {
+        let identifier = aliases.resolve(&param_identifier(&param.expr).expect("parsed as path or field"));
+        let usage = match &param.range {
+            Some(range) => PurtelParamUsage::with_range(&identifier, range.clone(), kind),
+            None => PurtelParamUsage::new(&identifier, kind),
+        };
+        if usages.iter().any(|u| u.identifier() == identifier && u.overlaps(&usage)) {
+            return Err(Error::new_spanned(&param.expr, format!("duplicate parameter `{}`", identifier)));
+        }
+        usages.push(usage);
+    }
+    Ok(usages)
+}
+
+/// Errors for each usage of `usages` (parsed from `params`) that is also in `others`,
+/// i.e. a parameter that is declared with two kinds.
+fn check_conflicts(params: &[PurtelParam],
+                   usages: &[PurtelParamUsage],
+                   others: &[PurtelParamUsage],
+                   hint: &str) -> syn::Result<()> {
+    for (param, usage) in params.iter().zip(usages) {
+        if let Some(other) = others.iter().find(|o| o.identifier() == usage.identifier() && o.overlaps(usage)) {
+            return Err(Error::new_spanned(
+                &param.expr,
+                format!(
+                    "parameter `{}` is declared as {:?} and {:?}; {}",
+                    usage.identifier(), usage.kind(), other.kind(), hint,
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the expression that constructs the given usage in the generated code.
+pub fn param_usage_tokens(krate: &Path, usage: &PurtelParamUsage) -> TokenStream {
+    let identifier = usage.identifier();
+    let kind = match usage.kind() {
+        PurtelParamUsageKind::READ => quote! { #krate::PurtelParamUsageKind::READ },
+        PurtelParamUsageKind::WRITE => quote! { #krate::PurtelParamUsageKind::WRITE },
+        PurtelParamUsageKind::REDUCE => quote! { #krate::PurtelParamUsageKind::REDUCE },
+        PurtelParamUsageKind::EXCLUSIVE => quote! { #krate::PurtelParamUsageKind::EXCLUSIVE },
+    };
+    match usage.range() {
+        Some(range) => {
+            let (start, end) = (range.start, range.end);
+            quote! { #krate::PurtelParamUsage::with_range(#identifier, #start..#end, #kind) }
+        },
+        None => quote! { #krate::PurtelParamUsage::new(#identifier, #kind) },
+    }
+}
+
+/// A `#[purtel_task(...)]`-attribute found inside the block of `#[purtel_tasks]`
+/// together with the closure (task) it is attached to.
+pub struct PurtelTaskMarker {
+    /// the attribute itself; contains the parameter usage
+    pub attr: Attribute,
+    /// name of the binding of the closure
+    pub closure: Ident,
+    /// parameter usage inferred from the body of the closure, see `infer.rs`
+    pub inferred: Vec<(String, PurtelParamUsageKind)>,
+    /// the clones in scope of the task; the declared parameters are resolved with them
+    pub aliases: ParamAliases,
+    /// index of the statement that defines the task
+    pub stmt_index: usize,
+    /// the task is the call of a `#[purtel_task] fn`; `closure` is bound to the
+    /// task and its parameter usage, see `function.rs`
+    pub function: bool,
+}
+
+impl PurtelTaskMarker {
+    pub fn new(attr: Attribute,
+               closure: Ident,
+               inferred: Vec<(String, PurtelParamUsageKind)>,
+               aliases: ParamAliases,
+               stmt_index: usize) -> Self {
+        Self {
+            attr,
+            closure,
+            inferred,
+            aliases,
+            stmt_index,
+            function: false,
+        }
+    }
+
+    /// Marks the task as call of a `#[purtel_task] fn`.
+    pub fn function(mut self) -> Self {
+        self.function = true;
+        self
+    }
+}
+
+/// The final parameter usage of a task: the explicit declaration of `#[purtel_task(...)]`
+/// merged with the usage inferred from the closure body.
+#[derive(Debug)]
+pub struct PurtelTaskParams {
+    pub write: Vec<PurtelParamUsage>,
+    pub read: Vec<PurtelParamUsage>,
+    pub reduce: Vec<PurtelParamUsage>,
+    pub exclusive: Vec<PurtelParamUsage>,
+    /// the explicitly declared parameters
+    pub declared: Vec<Expr>,
+}
+
+impl PurtelTaskParams {
+
+    /// An explicitly declared parameter overrides the inferred usage of the same
+    /// parameter; inferred parameters that are not declared get added. `aliases` are the
+    /// clones in scope of the task.
+    pub fn merge(attrs: &PurtelTaskAttributes,
+                 inferred: &[(String, PurtelParamUsageKind)],
+                 aliases: &ParamAliases) -> syn::Result<Self> {
+        let mut write = attrs.write_params(aliases)?;
+        let mut read = attrs.read_params(aliases)?;
+        let mut reduce = attrs.reduce_params(aliases)?;
+        let mut exclusive = attrs.exclusive_params(aliases)?;
+        for (identifier, kind) in inferred {
+            // also a declared range of the parameter overrides the inferred usage
+            if write.iter().chain(&read).chain(&reduce).chain(&exclusive).any(|usage| usage.identifier() == identifier) {
+                continue;
+            }
+            match kind {
+                PurtelParamUsageKind::WRITE => write.push(PurtelParamUsage::new(identifier, *kind)),
+                PurtelParamUsageKind::READ => read.push(PurtelParamUsage::new(identifier, *kind)),
+                PurtelParamUsageKind::REDUCE => reduce.push(PurtelParamUsage::new(identifier, *kind)),
+                PurtelParamUsageKind::EXCLUSIVE => exclusive.push(PurtelParamUsage::new(identifier, *kind)),
+            }
+        }
+        Ok(Self {
+            write,
+            read,
+            reduce,
+            exclusive,
+            declared: attrs.declared_exprs(),
+        })
     }
 }
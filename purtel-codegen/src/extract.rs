@@ -0,0 +1,196 @@
+//! Codegen. Extraction of the task definitions from the block of `#[purtel_tasks]`.
+
+use proc_macro2::Span;
+use quote::{format_ident, quote_spanned, ToTokens};
+use syn::{Attribute, Error, Expr, Pat, Stmt, parse_quote};
+use syn::spanned::Spanned;
+use purtel_core::PurtelParamUsageKind;
+use crate::data::{param_identifier, PurtelTaskMarker};
+use crate::function::{companion_ident, handles_ident};
+use crate::infer::{infer_param_usage, ParamAliases};
+
+/// Searches all top-level statements for `#[purtel_task]`-attributes. Allowed are
+/// - `#[purtel_task(...)] let task1 = move || ...;`
+/// - `let task1 = #[purtel_task(...)] move || ...;`
+/// - `#[purtel_task(...)] move || ...;` (the closure gets bound to a generated name)
+/// - `#[purtel_task] integrate(pos, vel);` (call of a `#[purtel_task] fn`)
+///
+/// Returns the statements without the attributes and a marker per task in order of
+/// declaration. A `#[purtel_task]`-attribute that isn't attached to a task and two tasks
+/// with the same name are errors.
+/// The parameter usage of each task gets inferred from its closure, see `infer.rs`.
+pub fn extract_purtel_tasks(stmts: Vec<Stmt>) -> syn::Result<(Vec<Stmt>, Vec<PurtelTaskMarker>)> {
+    let mut markers = vec![];
+    let mut out_stmts = vec![];
+    // clones of the shared data, like `let data1_t = data1.clone();`
+    let mut aliases = ParamAliases::default();
+
+    for mut stmt in stmts {
+        match stmt {
+            Stmt::Local(ref mut local) => {
+                let local_attr = take_purtel_task_attr(&mut local.attrs)?;
+                let closure_attr = match local.init {
+                    Some((_, ref mut init)) => take_closure_purtel_task_attr(init)?,
+                    None => None,
+                };
+                let attr = match (local_attr, closure_attr) {
+                    (Some(_), Some(closure_attr)) => {
+                        return Err(Error::new_spanned(closure_attr, "task is already annotated with #[purtel_task]"));
+                    },
+                    (Some(attr), None) | (None, Some(attr)) => attr,
+                    (None, None) => {
+                        if let Pat::Ident(ref pat_ident) = local.pat {
+                            let init = local.init.as_ref().map(|(_, init)| &**init);
+                            aliases.bind(&pat_ident.ident.to_string(), init);
+                        }
+                        out_stmts.push(stmt);
+                        continue;
+                    },
+                };
+                let closure = match local.pat {
+                    Pat::Ident(ref pat_ident) if local.init.is_some() => pat_ident.ident.clone(),
+                    _ => return Err(Error::new_spanned(
+                        &attr,
+                        "#[purtel_task] must be attached to a binding of a closure: `let task = move || ...;`",
+                    )),
+                };
+                let inferred = match local.init {
+                    Some((_, ref init)) => infer_closure(init, &aliases),
+                    None => vec![],
+                };
+                if markers.iter().any(|marker: &PurtelTaskMarker| marker.closure == closure) {
+                    return Err(Error::new_spanned(&closure, format!("duplicate task `{}`", closure)));
+                }
+                aliases.bind(&closure.to_string(), None);
+                markers.push(PurtelTaskMarker::new(attr, closure, inferred, aliases.clone(), out_stmts.len()));
+            },
+            Stmt::Expr(ref mut expr) | Stmt::Semi(ref mut expr, _) => {
+                if let Some(attr) = take_call_purtel_task_attr(expr)? {
+                    // call of a `#[purtel_task] fn`; the companion creates the task
+                    let closure = format_ident!("__purtel_task_{}", markers.len(), span = Span::mixed_site());
+                    let call = function_task_call(expr, &aliases)?;
+                    stmt = parse_quote!(let #closure = #call;);
+                    markers.push(PurtelTaskMarker::new(attr, closure, vec![], aliases.clone(), out_stmts.len()).function());
+                } else if let Some(attr) = take_closure_purtel_task_attr(expr)? {
+                    // anonymous task; we bind it (invisible for the caller) so that it can be moved
+                    // into the task vector
+                    let closure = format_ident!("__purtel_task_{}", markers.len(), span = Span::mixed_site());
+                    let inferred = infer_closure(expr, &aliases);
+                    stmt = parse_quote!(let #closure = #expr;);
+                    markers.push(PurtelTaskMarker::new(attr, closure, inferred, aliases.clone(), out_stmts.len()));
+                } else if let Some(attrs) = expr_attrs_mut(expr) {
+                    if let Some(attr) = take_purtel_task_attr(attrs)? {
+                        return Err(Error::new_spanned(
+                            attr,
+                            "#[purtel_task] must be attached to a closure or to a `let` binding of a closure",
+                        ));
+                    }
+                }
+            },
+            Stmt::Item(_) => {},
+        }
+        out_stmts.push(stmt);
+    }
+
+    Ok((out_stmts, markers))
+}
+
+/// Removes the `#[purtel_task]`-attribute from a function call and returns it.
+fn take_call_purtel_task_attr(expr: &mut Expr) -> syn::Result<Option<Attribute>> {
+    match expr {
+        Expr::Call(call) => take_purtel_task_attr(&mut call.attrs),
+        _ => Ok(None),
+    }
+}
+
+/// Maps `integrate(pos, vel)` to the call of the companion of the task function:
+/// `__purtel_task_integrate(["pos", "vel"], &pos, &vel)`. The names are the parameter
+/// identifiers; clones like `let pos_t = pos.clone();` are resolved. Arguments with the same
+/// name are checked against the constant `__PURTEL_TASK_HANDLES_integrate` of the function.
+fn function_task_call(expr: &Expr, aliases: &ParamAliases) -> syn::Result<Expr> {
+    let call = match expr {
+        Expr::Call(call) => call,
+        _ => unreachable!("only called for function calls"),
+    };
+    let mut function = match &*call.func {
+        Expr::Path(path) if path.qself.is_none() => path.path.clone(),
+        other => return Err(Error::new_spanned(other, "expected the name of a #[purtel_task] function")),
+    };
+    let mut handles = function.clone();
+    let last = function.segments.last_mut().expect("a path has at least one segment");
+    let function_ident = last.ident.clone();
+    last.ident = companion_ident(&function_ident);
+    handles.segments.last_mut().expect("a path has at least one segment").ident = handles_ident(&function_ident);
+
+    let mut names: Vec<String> = vec![];
+    // the task would lock the same data twice; only the signature tells if the parameters
+    // are handles (e.g. `integrate(pos, vel, 0.1, 0.1)` is fine), hence the check
+    // happens when the constant of the task function is evaluated
+    let mut checks = vec![];
+    for (i, arg) in call.args.iter().enumerate() {
+        let name = match param_identifier(arg) {
+            Some(name) => aliases.resolve(&name),
+            None => arg.to_token_stream().to_string(),
+        };
+        if let Some(first) = names.iter().position(|other| *other == name) {
+            let msg = format!("parameter `{}` is passed twice", name);
+            checks.push(quote_spanned! {arg.span()=>
+                const _: () = assert!(!(#handles[#first] && #handles[#i]), #msg);
+            });
+        }
+        names.push(name);
+    }
+    // type errors of the arguments point at the argument
+    let args = call.args.iter().map(|arg| quote_spanned! {arg.span()=> &#arg});
+    Ok(parse_quote! {
+        {
+            #(#checks)*
+            #function([#(#names),*], #(#args),*)
+        }
+    })
+}
+
+/// Infers the parameter usage if the expression is a closure.
+fn infer_closure(expr: &Expr, aliases: &ParamAliases) -> Vec<(String, PurtelParamUsageKind)> {
+    match expr {
+        Expr::Closure(closure) => infer_param_usage(closure, aliases),
+        _ => vec![],
+    }
+}
+
+/// Removes the `#[purtel_task]`-attribute from the attributes of a closure and returns it.
+fn take_closure_purtel_task_attr(expr: &mut Expr) -> syn::Result<Option<Attribute>> {
+    match expr {
+        Expr::Closure(closure) => take_purtel_task_attr(&mut closure.attrs),
+        _ => Ok(None),
+    }
+}
+
+/// Removes the `#[purtel_task]`-attribute from the list and returns it.
+fn take_purtel_task_attr(attrs: &mut Vec<Attribute>) -> syn::Result<Option<Attribute>> {
+    let mut found = attrs.iter()
+        .enumerate()
+        .filter(|(_, attr)| attr.path.is_ident("purtel_task"))
+        .map(|(i, _)| i);
+    match (found.next(), found.next()) {
+        (Some(_), Some(second)) => Err(Error::new_spanned(&attrs[second], "duplicate #[purtel_task]")),
+        (Some(i), None) => Ok(Some(attrs.remove(i))),
+        _ => Ok(None),
+    }
+}
+
+/// Returns the attributes of the most common kinds of expression statements.
+/// Used to detect `#[purtel_task]`-attributes that are not attached to a task,
+/// like the old marker syntax `#[purtel_task(...)] {}`.
+fn expr_attrs_mut(expr: &mut Expr) -> Option<&mut Vec<Attribute>> {
+    match expr {
+        Expr::Block(e) => Some(&mut e.attrs),
+        Expr::Call(e) => Some(&mut e.attrs),
+        Expr::MethodCall(e) => Some(&mut e.attrs),
+        Expr::Macro(e) => Some(&mut e.attrs),
+        Expr::Paren(e) => Some(&mut e.attrs),
+        Expr::Path(e) => Some(&mut e.attrs),
+        Expr::Unsafe(e) => Some(&mut e.attrs),
+        _ => None,
+    }
+}
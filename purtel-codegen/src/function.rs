@@ -0,0 +1,128 @@
+//! Codegen. `#[purtel_task]` on functions. The parameter usage follows from the signature:
+//! `&mut T` is WRITE, `&T` is READ, all other parameters are passed by value.
+//! ```ignore
+//! #[purtel_task]
+//! fn integrate(pos: &mut Positions, vel: &Velocities, dt: f64) { ... }
+//! ```
+//! additionally generates the companion
+//! ```ignore
+//! fn __purtel_task_integrate<H0, H1>(names: [&str; 3], pos: &H0, vel: &H1, dt: &f64)
+//!     -> (purtel::PurtelTask, Vec<purtel::PurtelParamUsage>)
+//!     where H0: purtel::PurtelSharedHandle<Target = Positions> + Send + 'static, ...
+//! ```
+//! that clones the shared handles into a task, and the constant `__PURTEL_TASK_HANDLES_integrate`
+//! (`[true, true, false]`). `#[purtel_task] integrate(pos, vel, 0.1);` inside a purtel block
+//! calls the companion with the names of the arguments.
+
+use darling::FromMeta;
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Error, FnArg, Ident, ItemFn, Path, Type, parse_quote};
+
+/// Attributes of `#[purtel_task(...)]` on a function.
+#[derive(Debug, Default, FromMeta)]
+pub struct PurtelTaskFnAttributes {
+    /// `#[purtel_task(crate = "purtel_core")]`: path to the crate with the purtel types
+    #[darling(default, rename = "crate")]
+    krate: Option<Path>,
+}
+
+/// Name of the companion of a task function.
+pub fn companion_ident(function: &Ident) -> Ident {
+    format_ident!("__purtel_task_{}", function)
+}
+
+/// Name of the constant that tells for each parameter of a task function if it is a shared
+/// handle (a reference in the signature). Only handles must not be passed twice.
+pub fn handles_ident(function: &Ident) -> Ident {
+    format_ident!("__PURTEL_TASK_HANDLES_{}", function)
+}
+
+/// Returns the function unchanged together with its companion.
+pub fn expand_task_fn(attrs: &PurtelTaskFnAttributes, function: ItemFn) -> syn::Result<TokenStream> {
+    let sig = &function.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(asyncness, "#[purtel_task] doesn't support async functions"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(&sig.generics, "#[purtel_task] doesn't support generic functions"));
+    }
+
+    let krate = attrs.krate.clone().unwrap_or_else(|| parse_quote!(purtel));
+    let name = &sig.ident;
+    let companion = companion_ident(name);
+    let handles_const = handles_ident(name);
+    let vis = &function.vis;
+    let arg_count = sig.inputs.len();
+    // local identifiers of the companion; must not shadow the function
+    let names = Ident::new("names", Span::mixed_site());
+    let usages = Ident::new("usages", Span::mixed_site());
+
+    let mut args = vec![];
+    let mut arg_types = vec![];
+    let mut call_args = vec![];
+    let mut handles = vec![];
+    let mut handle_bounds = vec![];
+    let mut param_usages = vec![];
+    let mut is_handle = vec![];
+    for (i, input) in sig.inputs.iter().enumerate() {
+        let pat_type = match input {
+            FnArg::Typed(pat_type) => pat_type,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(receiver, "#[purtel_task] doesn't support methods"));
+            },
+        };
+        let arg = Ident::new(&format!("arg{}", i), Span::mixed_site());
+        match &*pat_type.ty {
+            Type::Reference(reference) => {
+                let handle = format_ident!("H{}", i);
+                let target = &reference.elem;
+                handle_bounds.push(quote! {
+                    #handle: #krate::PurtelSharedHandle<Target = #target> + Send + 'static
+                });
+                let kind = if reference.mutability.is_some() {
+                    call_args.push(quote! { &mut *#krate::PurtelSharedHandle::write_guard(&#arg) });
+                    quote! { WRITE }
+                } else {
+                    call_args.push(quote! { &*#krate::PurtelSharedHandle::read_guard(&#arg) });
+                    quote! { READ }
+                };
+                param_usages.push(quote! {
+                    #krate::PurtelParamUsage::new(#names[#i], #krate::PurtelParamUsageKind::#kind)
+                });
+                arg_types.push(quote! { #handle });
+                handles.push(handle);
+            },
+            // passed by value
+            ty => {
+                call_args.push(quote! { #arg });
+                arg_types.push(quote! { #ty });
+            },
+        }
+        is_handle.push(matches!(&*pat_type.ty, Type::Reference(_)));
+        args.push(arg);
+    }
+
+    Ok(quote! {
+        #function
+
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals)]
+        #vis const #handles_const: [bool; #arg_count] = [#(#is_handle),*];
+
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::type_complexity)]
+        #vis fn #companion<#(#handles),*>(#names: [&str; #arg_count], #(#args: &#arg_types),*)
+            -> (#krate::PurtelTask, ::std::vec::Vec<#krate::PurtelParamUsage>)
+            where #(#handle_bounds),*
+        {
+            let _ = &#names;
+            let #usages = vec![#(#param_usages),*];
+            #(let #args = ::std::clone::Clone::clone(#args);)*
+            let task = #krate::PurtelTask::new(Box::new(move || {
+                #name(#(#call_args),*);
+            }));
+            (task, #usages)
+        }
+    })
+}
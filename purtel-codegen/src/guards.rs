@@ -0,0 +1,85 @@
+//! Codegen. Clone-and-move boilerplate and lock acquisition for `#[purtel_task(..., guards)]`.
+//! Parameters in `exclusive(...)` get the write guard, parameters in `reduce(...)` are only
+//! cloned into the task.
+//! ```ignore
+//! #[purtel_task(read(data1), write(data2), guards)]
+//! let task1 = move || { data2.push(data1[0]); };
+//! ```
+//! becomes
+//! ```ignore
+//! let task1 = {
+//!     let data1 = data1.clone();
+//!     let data2 = data2.clone();
+//!     move || {
+//!         let data1 = purtel::PurtelSharedHandle::read_guard(&data1);
+//!         let mut data2 = purtel::PurtelSharedHandle::write_guard(&data2);
+//!         { data2.push(data1[0]); }
+//!     }
+//! };
+//! ```
+
+use syn::{Error, Expr, Ident, Path, Stmt, Token, parse_quote};
+use crate::data::PurtelTaskAttributes;
+
+/// Wraps the closure of the task statement (`let task = move || ...;`) as shown above.
+pub fn bind_guards(krate: &Path, stmt: &mut Stmt, attrs: &PurtelTaskAttributes) -> syn::Result<()> {
+    let init = match stmt {
+        Stmt::Local(local) => local.init.as_mut().map(|(_, init)| &mut **init),
+        _ => None,
+    };
+    let init = match init {
+        Some(init) => init,
+        None => return Err(Error::new_spanned(stmt, "`guards` needs a closure as task")),
+    };
+    let mut closure = match init {
+        Expr::Closure(closure) => closure.clone(),
+        other => return Err(Error::new_spanned(other, "`guards` needs a closure as task")),
+    };
+
+    // a guard is always for the whole data; two tasks of disjoint ranges would conflict
+    if let Some(param) = attrs.ranged_expr() {
+        return Err(Error::new_spanned(
+            param,
+            "`guards` doesn't support ranges; use e.g. `data1.write(0..1024)` of a `PurtelSlice` in the task",
+        ));
+    }
+
+    // exclusive access needs the write guard as well
+    let writes = attrs.write_exprs()
+        .chain(attrs.exclusive_exprs())
+        .map(param_variable)
+        .collect::<syn::Result<Vec<_>>>()?;
+    let reads = attrs.read_exprs().map(param_variable).collect::<syn::Result<Vec<_>>>()?;
+    // a reduction has no guard; the task calls `reduce()` on the handle
+    let reduces = attrs.reduce_exprs().map(param_variable).collect::<syn::Result<Vec<_>>>()?;
+    let params = writes.iter().chain(reads.iter()).chain(reduces.iter());
+
+    // the clones must be moved into the closure
+    closure.capture = Some(<Token![move]>::default());
+    let body = &closure.body;
+    closure.body = parse_quote! {
+        {
+            #(
+                #[allow(unused_mut)]
+                let mut #writes = #krate::PurtelSharedHandle::write_guard(&#writes);
+            )*
+            #(let #reads = #krate::PurtelSharedHandle::read_guard(&#reads);)*
+            #body
+        }
+    };
+    *init = parse_quote! {
+        {
+            #(let #params = ::std::clone::Clone::clone(&#params);)*
+            #closure
+        }
+    };
+    Ok(())
+}
+
+/// The guard gets bound to the name of the parameter, hence it must be a variable.
+fn param_variable(param: &Expr) -> syn::Result<Ident> {
+    match param {
+        Expr::Path(path) if path.path.get_ident().is_some() => Ok(path.path.get_ident().unwrap().clone()),
+        _ => Err(Error::new_spanned(param, "`guards` needs parameters that are variables")),
+    }
+}
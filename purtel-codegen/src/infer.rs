@@ -0,0 +1,200 @@
+//! Codegen. Inference of the parameter usage of a task from the body of its closure.
+//! A call of `read()`/`try_read()` on a captured variable means READ, a call of
+//! `write()`/`try_write()` means WRITE. This works for `RwLock`, `PurtelShared`,
+//! `PurtelCell` and `PurtelReduction`, whose `reduce(...)` means REDUCE.
+
+use std::collections::{HashMap, HashSet};
+use syn::{Arm, Block, Expr, ExprClosure, ExprForLoop, ExprIf, ExprLet, ExprMethodCall, ExprWhile, Item, Local, PatIdent};
+use syn::visit::{self, Visit};
+use purtel_core::PurtelParamUsageKind;
+use crate::data::param_identifier;
+
+/// Tracks `let data1_t = data1.clone();`-bindings in the block of `#[purtel_tasks]`, so that
+/// an access to `data1_t` inside a closure is attributed to the parameter `data1`.
+#[derive(Debug, Default, Clone)]
+pub struct ParamAliases {
+    /// binding name => parameter identifier
+    aliases: HashMap<String, String>,
+}
+
+impl ParamAliases {
+
+    /// Updates the aliases for a `let`-binding of `name` to the expression `init`.
+    /// Shadowing removes a previous alias.
+    pub fn bind(&mut self, name: &str, init: Option<&Expr>) {
+        match init.and_then(cloned_variable) {
+            Some(source) => {
+                let identifier = self.resolve(&source);
+                self.aliases.insert(name.to_owned(), identifier);
+            },
+            None => {
+                self.aliases.remove(name);
+            },
+        }
+    }
+
+    /// Returns the parameter identifier for a variable name or a field of a
+    /// variable (`state_t.positions` => `state.positions`).
+    pub fn resolve(&self, name: &str) -> String {
+        if let Some(identifier) = self.aliases.get(name) {
+            return identifier.clone();
+        }
+        match name.split_once('.') {
+            Some((root, fields)) => format!("{}.{}", self.resolve(root), fields),
+            None => name.to_owned(),
+        }
+    }
+}
+
+/// Returns the name of the variable `x` if the expression is `x.clone()` or `Arc::clone(&x)`;
+/// also for fields like `state.positions.clone()`.
+fn cloned_variable(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::MethodCall(call) if call.method == "clone" && call.args.is_empty() => {
+            param_identifier(&call.receiver)
+        },
+        Expr::Call(call) if call.args.len() == 1 => {
+            let is_arc_clone = match &*call.func {
+                Expr::Path(path) => {
+                    let segments = path.path.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<_>>();
+                    segments.ends_with(&["Arc".to_owned(), "clone".to_owned()])
+                },
+                _ => false,
+            };
+            match call.args.first() {
+                Some(Expr::Reference(reference)) if is_arc_clone => param_identifier(&reference.expr),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Infers the parameter usage of a task from its closure. Returns each parameter only once;
+/// Different kinds of the same parameter join to WRITE. The order is the order of the first access.
+pub fn infer_param_usage(closure: &ExprClosure, aliases: &ParamAliases) -> Vec<(String, PurtelParamUsageKind)> {
+    let mut visitor = AccessVisitor::default();
+    visitor.visit_expr_closure(closure);
+
+    let mut usages: Vec<(String, PurtelParamUsageKind)> = vec![];
+    for (variable, kind) in visitor.accesses {
+        let identifier = aliases.resolve(&variable);
+        match usages.iter_mut().find(|(id, _)| *id == identifier) {
+            Some(usage) => usage.1 = usage.1.join(kind),
+            None => usages.push((identifier, kind)),
+        }
+    }
+    usages
+}
+
+/// Collects all lock method calls on captured variables (or their fields). Variables that are
+/// declared inside the closure (including its parameters) are no shared data, but only from
+/// their declaration to the end of their scope: in `let d = d.write().unwrap();` the access
+/// is on the captured `d`, like an access after the block that declares a `d`.
+#[derive(Default)]
+struct AccessVisitor {
+    accesses: Vec<(String, PurtelParamUsageKind)>,
+    /// the variables declared in each scope that encloses the current expression
+    scopes: Vec<HashSet<String>>,
+}
+
+impl AccessVisitor {
+
+    fn is_local(&self, variable: &str) -> bool {
+        let root = variable.split(['.', ':']).next().unwrap_or_default();
+        self.scopes.iter().any(|scope| scope.contains(root))
+    }
+
+    /// Visits `f` in a new scope; the bindings made by `f` end with it.
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashSet::new());
+        f(self);
+        self.scopes.pop();
+    }
+}
+
+impl<'ast> Visit<'ast> for AccessVisitor {
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        // the lock methods have no arguments, unlike e.g. `std::io::Read::read(&mut buf)`;
+        // `PurtelReduction::reduce()` takes the update
+        let kind = match (call.method.to_string().as_str(), call.args.len()) {
+            ("read" | "try_read", 0) => Some(PurtelParamUsageKind::READ),
+            ("write" | "try_write", 0) => Some(PurtelParamUsageKind::WRITE),
+            ("reduce", 1) => Some(PurtelParamUsageKind::REDUCE),
+            _ => None,
+        };
+        if let (Some(kind), Some(variable)) = (kind, param_identifier(&call.receiver)) {
+            if !self.is_local(&variable) {
+                self.accesses.push((variable, kind));
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_pat_ident(&mut self, pat: &'ast PatIdent) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(pat.ident.to_string());
+        }
+        visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.scoped(|this| visit::visit_block(this, block));
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        // the initializer is evaluated before the binding exists
+        if let Some((_, init)) = &local.init {
+            self.visit_expr(init);
+        }
+        self.visit_pat(&local.pat);
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast ExprClosure) {
+        self.scoped(|this| {
+            for input in &closure.inputs {
+                this.visit_pat(input);
+            }
+            this.visit_expr(&closure.body);
+        });
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        self.scoped(|this| visit::visit_arm(this, arm));
+    }
+
+    fn visit_expr_for_loop(&mut self, for_loop: &'ast ExprForLoop) {
+        self.visit_expr(&for_loop.expr);
+        self.scoped(|this| {
+            this.visit_pat(&for_loop.pat);
+            this.visit_block(&for_loop.body);
+        });
+    }
+
+    fn visit_expr_if(&mut self, expr_if: &'ast ExprIf) {
+        // the bindings of `if let` are only visible in the then-branch
+        self.scoped(|this| {
+            this.visit_expr(&expr_if.cond);
+            this.visit_block(&expr_if.then_branch);
+        });
+        if let Some((_, else_branch)) = &expr_if.else_branch {
+            self.visit_expr(else_branch);
+        }
+    }
+
+    fn visit_expr_while(&mut self, expr_while: &'ast ExprWhile) {
+        self.scoped(|this| {
+            this.visit_expr(&expr_while.cond);
+            this.visit_block(&expr_while.body);
+        });
+    }
+
+    fn visit_expr_let(&mut self, expr_let: &'ast ExprLet) {
+        self.visit_expr(&expr_let.expr);
+        self.visit_pat(&expr_let.pat);
+    }
+
+    fn visit_item(&mut self, _item: &'ast Item) {
+        // nested items can't capture the variables of the closure
+    }
+}
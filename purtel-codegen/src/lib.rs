@@ -1,54 +1,174 @@
 //! Codegen. Definition and export of procedural macros.
 
 use darling::FromMeta;
-use syn::AttributeArgs;
+use syn::{AttributeArgs, Attribute, Block, DeriveInput, Error, ItemFn, Meta, parse_macro_input};
+use syn::parse::{Parser, ParseStream};
+use syn::spanned::Spanned;
+use quote::{quote, quote_spanned};
 // See https://crates.io/crates/proc-macro2:
 // it's recommended to always use proc_macro2-exports
 // proc_macro: rust internal library
 // proc_macro2: library from crates.io
 use proc_macro::TokenStream as TokenStream1;
-use proc_macro2::{TokenStream as TokenStream2, TokenTree as TokenTree2, Group as Group2};
-use syn::parse_macro_input;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 
 mod util;
 mod data;
-use data::PurtelTaskAttributes;
+mod extract;
+mod guards;
+mod function;
+mod state;
+mod infer;
+mod schedule;
+use purtel_core::PurtelError;
+use data::{PurtelTaskAttributes, PurtelTaskParams, PurtelTasksAttributes, param_identifier, param_usage_tokens};
 use crate::util::unwrap_block;
+use crate::extract::extract_purtel_tasks;
+use crate::guards::bind_guards;
+use crate::function::{expand_task_fn, PurtelTaskFnAttributes};
+use crate::state::expand_purtel_state;
+use crate::schedule::static_schedule;
 
-/// This macro should be around a block that contains all purtel task definitions
-/// (or at least `[purtel_task]`).
+/// This macro should be around a block that contains all purtel task definitions.
+/// Each task is a closure that is annotated with `#[purtel_task]`, see there.
+/// Needs nightly Rust because it is an attribute on a block statement;
+/// see `purtel_block!` for stable Rust.
+///
+/// By default the block gets unwrapped and the vectors `closures` and `param_usages`
+/// as well as the constant `PURTEL_SCHEDULE` (dependencies and execution levels,
+/// calculated at compile time) are generated. With `#[purtel_tasks(run)]` the block becomes a single parallel region
+/// instead: it builds, plans and runs the `PurtelExecutor` and evaluates to the result
+/// of `PurtelExecutor::execute()`.
+///
+/// Further options:
+/// - `name = "tasks"`: generates only the binding `tasks`, a `PurtelTaskGraph`,
+///   instead of `closures`, `param_usages` and `PURTEL_SCHEDULE`
+/// - `closures = "..."`, `param_usages = "..."`, `schedule = "..."`: other names for
+///   these three bindings. Without them the generated code still uses the fixed names
+///   in the scope of the caller, because existing code refers to them; all other
+///   identifiers of the generated code are hygienic
+/// - `crate = "purtel_core"`: path to the purtel types in the generated code;
+///   default is `purtel`
 #[proc_macro_attribute]
-pub fn purtel_tasks(_attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
+pub fn purtel_tasks(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
+    let attr_args = parse_macro_input!(attr as AttributeArgs);
+    let options = match PurtelTasksAttributes::from_list(&attr_args) {
+        Ok(options) => options,
+        Err(err) => return err.write_errors().into(),
+    };
+
     // as recommended: always use "proc_macro2" in prodecural macros
-    let mut item = TokenStream2::from(item);
+    let item = TokenStream2::from(item);
 
     // print_expanded_token_stream(&item);
 
     /////////////////////////////////////////////////////////////////
-    // Part 1/2: unwrap block
+    // Part 1/3: unwrap block
+
+    let item = match unwrap_block(item) {
+        Ok(item) => item,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    // Transform TokenStream2 back to TokenSteam1
+    expand_purtel_tasks(&options, item).unwrap_or_else(|err| err).into()
+}
+
+/// Function-like alternative to `#[purtel_tasks]` that works on stable Rust. Accepts the
+/// same task syntax as the content of the block of `#[purtel_tasks]`:
+/// ```ignore
+/// purtel_block! {
+///     let data1_t = data1.clone();
+///     #[purtel_task(read(data1))]
+///     let task1 = move || { ... };
+/// }
+/// ```
+/// (It can't be called `purtel_tasks!` because a crate can't export a function-like
+/// and an attribute macro with the same name.)
+///
+/// The options of `#[purtel_tasks(...)]` are given as inner attribute:
+/// `let result = purtel_block! { #![purtel_tasks(run)] ... };`
+#[proc_macro]
+pub fn purtel_block(item: TokenStream1) -> TokenStream1 {
+    let parse_content = |input: ParseStream| {
+        let attrs = input.call(Attribute::parse_inner)?;
+        let stmts = input.call(Block::parse_within)?;
+        Ok((attrs, stmts))
+    };
+    let (attrs, stmts) = match parse_content.parse(item) {
+        Ok(content) => content,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
-    item = unwrap_block(item);
+    let mut options = None;
+    for attr in &attrs {
+        if !attr.path.is_ident("purtel_tasks") {
+            let err = Error::new_spanned(attr, "only `#![purtel_tasks(...)]` is allowed as inner attribute");
+            return err.to_compile_error().into();
+        }
+        if options.is_some() {
+            return Error::new_spanned(attr, "duplicate #![purtel_tasks]").to_compile_error().into();
+        }
+        let attr_args = match attr_args(attr) {
+            Ok(attr_args) => attr_args,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        match PurtelTasksAttributes::from_list(&attr_args) {
+            Ok(parsed) => options = Some(parsed),
+            Err(err) => return err.write_errors().into(),
+        }
+    }
+
+    expand_purtel_tasks(&options.unwrap_or_default(), quote! { #(#stmts)* })
+        .unwrap_or_else(|err| err)
+        .into()
+}
+
+/// Returns the arguments of an attribute like `#[purtel_task(...)]`; none for just `#[purtel_task]`.
+fn attr_args(attr: &Attribute) -> syn::Result<AttributeArgs> {
+    match attr.parse_meta()? {
+        Meta::List(list) => Ok(list.nested.into_iter().collect()),
+        Meta::Path(_) => Ok(vec![]),
+        meta @ Meta::NameValue(_) => Err(Error::new_spanned(meta, "expected a list like `#[purtel_task(...)]`")),
+    }
+}
+
+/// Shared implementation of `#[purtel_tasks]` and `purtel_block!`. Takes the statements
+/// (the content of the block) and returns them together with the generated code.
+/// The error is a `compile_error!` invocation spanned at the offending tokens.
+fn expand_purtel_tasks(options: &PurtelTasksAttributes, item: TokenStream2) -> Result<TokenStream2, TokenStream2> {
+    let stmts = Block::parse_within.parse2(item).map_err(|err| err.to_compile_error())?;
 
 
     /////////////////////////////////////////////////////////////////
-    // Part 2/2: generate dependencies vector from metadata annotations
+    // Part 2/3: generate dependencies vector from metadata annotations
+
+    // we get all #[purtel_task()]-attributes together with the closure they are
+    // attached to; the attributes are removed from the statements
+    let (mut stmts, markers) = extract_purtel_tasks(stmts).map_err(|err| err.to_compile_error())?;
 
-    // we get all token streams for the #[purtel_task()]-macros
-    // parse_macro_input!()- works only when called in the proc macro function itself
-    let mut token_streams = vec![];
-    purtel_task_traverse_token_stream(&item, &mut token_streams);
+    // path to the purtel types; `purtel` or e.g. `purtel_core`
+    let krate = options.krate();
 
-    // we parse each token stream
+    // we parse each attribute
     let mut attributes = vec![];
-    for ts in token_streams {
-        let ts = ts.into();
-        // parse_macro_input!()- works only when called in the proc macro function itself,
-        // otherwise weird errors :/
-        let attr_args = parse_macro_input!(ts as AttributeArgs);
-        // from_list() gets generated from derive-annotation on the struct
-        let attrs: PurtelTaskAttributes = PurtelTaskAttributes::from_list(&attr_args)
-            .expect("#[purtel_task] could not be parsed!");
-        attributes.push(attrs);
+    let mut after_names = vec![];
+    for marker in &markers {
+        if marker.function && !marker.attr.tokens.is_empty() {
+            let err = Error::new_spanned(
+                &marker.attr,
+                "the parameter usage of a #[purtel_task] fn follows from its signature; use just `#[purtel_task]`",
+            );
+            return Err(err.to_compile_error());
+        }
+        let attrs = PurtelTaskAttributes::from_attr(&marker.attr).map_err(|err| err.to_compile_error())?;
+        // explicit declarations override the usage inferred from the closure body
+        let params = PurtelTaskParams::merge(&attrs, &marker.inferred, &marker.aliases).map_err(|err| err.to_compile_error())?;
+        if attrs.guards() {
+            bind_guards(&krate, &mut stmts[marker.stmt_index], &attrs).map_err(|err| err.to_compile_error())?;
+        }
+        attributes.push(params);
+        after_names.push(attrs.after().to_vec());
     }
 
     // prints out param usage per task (index)
@@ -56,99 +176,252 @@ pub fn purtel_tasks(_attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
         println!("task {} uses: read={:?}, write={:?}", i, x.read, x.write);
     }*/
 
-    // TODO check how this whole section can be make less ugly
-    // with quote!{} macro
-
-    let mut str = String::from("let param_usages = vec![\n");
-    for (_task_i, param_attr) in attributes.iter().enumerate() {
-        str.push_str("  vec![\n");
-        for write_param in &param_attr.write_params() {
-            str.push_str(
-                &format!("    purtel::PurtelParamUsage::new(\
-                        \"{}\", purtel::PurtelParamUsageKind::WRITE),", write_param)
-            )
-        };
-        for read_param in &param_attr.read_params() {
-            str.push_str(
-                &format!("    purtel::PurtelParamUsage::new(\
-                        \"{}\", purtel::PurtelParamUsageKind::READ),", read_param)
-            )
+    let param_usages = markers.iter().zip(&attributes).map(|(marker, params)| {
+        if marker.function {
+            // the companion of the function created the param usage
+            let task = &marker.closure;
+            return quote! { #task.1 };
         }
-        str.push_str("  ],\n");
-    }
-    str.push_str("];");
-    // println!("{}", str);
+        let usages = params.write.iter()
+            .chain(&params.read)
+            .chain(&params.reduce)
+            .chain(&params.exclusive)
+            .map(|usage| param_usage_tokens(&krate, usage));
+        quote! {
+            vec![#(#usages),*]
+        }
+    }).collect::<Vec<_>>();
 
-    item.extend(str.parse::<TokenStream2>().unwrap());
+    /////////////////////////////////////////////////////////////////
+    // Part 3/3: generate vector with all tasks; same order as param usages
 
-    item.into() // Transform TokenStream2 back to TokenSteam1
-}
+    let tasks = markers.iter().map(|marker| {
+        let closure = &marker.closure;
+        if marker.function {
+            quote! { #closure.0 }
+        } else {
+            quote! { #krate::PurtelTask::new(Box::from(#closure)) }
+        }
+    }).collect::<Vec<_>>();
 
-/// Constructs a TokenStream out of the stream of a group.
-/// Must be called like that: `&group.stream()`.
-fn group_to_token_stream(group: &TokenStream2) -> TokenStream2 {
-    let mut nts = TokenStream2::new();
-
-    for x in group.clone() {
-        match x {
-            TokenTree2::Group(ref group) => {
-                // print!("group:");
-                let group_ts = group_to_token_stream(&group.stream());
-                let group = Group2::new(group.delimiter(), group_ts);
-                nts.extend(vec![TokenTree2::Group(group)]);
-            },
-            TokenTree2::Ident(ref _ident) => {
-                nts.extend(vec![x]);
-            },
-            TokenTree2::Punct(ref _punct) => {
-                nts.extend(vec![x]);
-            },
-            TokenTree2::Literal(ref _literal) => {
-                nts.extend(vec![x]);
+    // each declared parameter must be a shared handle in scope; the check is placed
+    // before the task, where the variable hasn't been moved into a closure yet
+    let mut checked_stmts = TokenStream2::new();
+    for (i, stmt) in stmts.iter().enumerate() {
+        let declared = markers.iter()
+            .zip(&attributes)
+            .filter(|(marker, _)| marker.stmt_index == i)
+            .flat_map(|(marker, params)| params.declared.iter().map(move |param| (marker, param)));
+        for (marker, param) in declared {
+            checked_stmts.extend(quote_spanned! {param.span()=>
+                #krate::purtel_check_shared_handle(&#param);
+            });
+            // the identifier of a field like `state.positions` is only known at runtime,
+            // see `#[derive(PurtelState)]`
+            let identifier = param_identifier(param).map(|identifier| marker.aliases.resolve(&identifier));
+            if let Some(identifier) = identifier.filter(|identifier| identifier.contains('.')) {
+                checked_stmts.extend(quote_spanned! {param.span()=>
+                    #krate::purtel_check_declared_identifier(&#param, #identifier);
+                });
             }
         }
+        checked_stmts.extend(quote! { #stmt });
     }
 
-    nts
-}
+    // explicit dependencies `(task, after)`; `after` refers to the name of a task
+    let mut dependencies = vec![];
+    let mut dependency_names = vec![];
+    for (task_id, names) in after_names.iter().enumerate() {
+        for name in names {
+            let after = markers.iter().position(|other| other.closure == *name).ok_or_else(|| {
+                let msg = format!("unknown task `{}`; expected the name of a task of this block", name);
+                Error::new_spanned(name, msg).to_compile_error()
+            })?;
+            dependencies.push((task_id, after));
+            dependency_names.push(name.clone());
+        }
+    }
 
-/// Traverses the token stream and searches for all #[purtel_task]-Annotations. It extracts
-/// their TokenStream and stores it into a vector. It's a recursive function.
-fn purtel_task_traverse_token_stream(stream: &TokenStream2, token_streams: &mut Vec<TokenStream2>) {
-    let mut next_group_is_purtel_task = false;
-    for tree in stream.clone() {
-        match tree {
-            TokenTree2::Group(ref group) => {
-                // println!("group:");
-                if next_group_is_purtel_task {
-                    let ts = group_to_token_stream(&group.stream());
-                    token_streams.push(ts);
-                    next_group_is_purtel_task = false;
-                }
-                purtel_task_traverse_token_stream(&group.stream(), token_streams);
-            },
-            TokenTree2::Ident(ref ident) => {
-                // println!("ident: '{}', ", ident);
-                if ident.to_string() == "purtel_task" {
-                    next_group_is_purtel_task = true;
+    // all parameter usages are known now, hence we can plan at compile time;
+    // except for tasks from `#[purtel_task] fn`s: their usage is known at runtime
+    // (but a cycle of the explicit dependencies is already an error)
+    let function_task = markers.iter().find(|marker| marker.function);
+    let schedule = static_schedule(&krate, &attributes, &dependencies).map_err(|err| {
+        // every cycle contains an explicit dependency; derived ones point to lower IDs
+        let in_cycle = |(task_id, after): &(usize, usize)| match &err {
+            PurtelError::DependencyCycle(cycle) => (0..cycle.len())
+                .any(|i| cycle[i] == *task_id && cycle[(i + 1) % cycle.len()] == *after),
+            _ => false,
+        };
+        let i = dependencies.iter().position(in_cycle).unwrap_or(0);
+        Error::new_spanned(&dependency_names[i], err.to_string()).to_compile_error()
+    })?;
+    let dependencies = dependencies.iter().map(|(task_id, after)| quote! { (#task_id, #after) });
+    let with_dependencies = if dependency_names.is_empty() {
+        quote! {}
+    } else {
+        quote! { .with_dependencies(vec![#(#dependencies),*]) }
+    };
+
+    // identifiers that only the generated code uses; invisible for the caller
+    let graph = options.name.clone().unwrap_or_else(|| Ident::new("purtel_graph", Span::mixed_site()));
+    let schedule_const = Ident::new("PURTEL_SCHEDULE", Span::mixed_site());
+
+    if let (Some(binding), true) = (options.binding_name(), options.name.is_some() || options.run) {
+        let err = Error::new_spanned(
+            binding,
+            "the names of the separate bindings can't be combined with `name` or `run`",
+        );
+        return Err(err.to_compile_error());
+    }
+
+    let output = if options.name.is_some() || options.run {
+        let graph_expr = if function_task.is_some() {
+            quote! {
+                #krate::PurtelTaskGraph::without_schedule(vec![#(#tasks),*], vec![#(#param_usages),*])
+                    #with_dependencies
+            }
+        } else {
+            quote! {
+                #krate::PurtelTaskGraph::new(
+                    vec![#(#tasks),*],
+                    vec![#(#param_usages),*],
+                    {
+                        const #schedule_const: #krate::PurtelStaticSchedule = #schedule;
+                        #schedule_const
+                    },
+                )
+                #with_dependencies
+            }
+        };
+        let output = quote! {
+            #checked_stmts
+            let #graph = #graph_expr;
+        };
+        if options.run {
+            // one parallel region: all bindings stay inside the block
+            // and the block evaluates to the execution result
+            quote! {
+                {
+                    #output
+                    #graph.into_executor().execute()
                 }
-            },
-            _ => {}
+            }
+        } else {
+            output
         }
-    }
+    } else if let Some(marker) = function_task {
+        let err = Error::new_spanned(
+            &marker.attr,
+            "tasks from #[purtel_task] fns are planned at runtime; \
+             use `#[purtel_tasks(name = \"...\")]` or `#[purtel_tasks(run)]`",
+        );
+        return Err(err.to_compile_error());
+    } else if let Some(after) = dependency_names.first() {
+        // `PurtelExecutor::new(closures, param_usages)` wouldn't know the dependencies
+        let err = Error::new_spanned(
+            after,
+            "`after` needs `#[purtel_tasks(name = \"...\")]` or `#[purtel_tasks(run)]`; \
+             the separate bindings don't contain the explicit dependencies",
+        );
+        return Err(err.to_compile_error());
+    } else {
+        // without a name we generate the bindings "param_usages" and "closures"
+        // and the constant "PURTEL_SCHEDULE" (or the chosen names) in the scope of the caller
+        let param_usages_name = options.param_usages();
+        let closures_name = options.closures();
+        let schedule_name = options.schedule();
+        quote! {
+            #checked_stmts
+            let #param_usages_name = vec![#(#param_usages),*];
+            let #closures_name = vec![#(#tasks),*];
+            const #schedule_name: #krate::PurtelStaticSchedule = #schedule;
+        }
+    };
+
+    Ok(output)
 }
 
-/// Invoked like this:
-/// - `#[purtel_task(write = "data1, data2")]`
-/// - `#[purtel_task(write = "data1", read = "data2")]`
-/// - `#[purtel_task(read = "data2")]`
-/// If a parameter is write it is automatically also read.
+/// Attached to a task (closure) inside a `#[purtel_tasks]`-block or `purtel_block!`. Invoked like this:
+/// - `#[purtel_task(write(data1, data2))] let task1 = move || { ... };`
+/// - `let task2 = #[purtel_task(write(data1), read(data2))] move || { ... };`
+/// - `#[purtel_task(read(data2))] move || { ... };`
+/// - `#[purtel_task] let task3 = move || { ... };`
+///
+/// If a parameter is write it is automatically also read; declaring it in both is an error.
+/// Besides `read` and `write` a parameter can be declared as `reduce` (commutative updates,
+/// see `PurtelReduction`) or `exclusive` (exclusive access in any order, like OpenMP's
+/// `mutexinoutset`). `write(data1[0..1024])` or `read(data1[3])` only declares a range of
+/// the parameter, e.g. a chunk of a `PurtelSlice`; the bounds must be integer literals
+/// because the schedule is calculated at compile time.
+///
+/// The declaration is optional: `#[purtel_tasks]` infers the parameter usage from calls
+/// of `read()`/`try_read()` and `write()`/`try_write()` on captured variables inside the
+/// closure. Clones like `let data1_t = data1.clone();` count as the parameter `data1`,
+/// in the closure as well as in a declaration like `write(data1_t)`.
+/// An explicitly declared parameter overrides the inferred usage of that parameter;
+/// use it for accesses that can't be seen syntactically (e.g. inside called functions).
+///
+/// With `guards` the declared parameters get cloned into the task and their guards
+/// are bound under the same names at the beginning of the closure body, so the task
+/// only contains its logic:
+/// `#[purtel_task(read(data1), write(data2), guards)] let task = move || data2.push(data1[0]);`
+///
+/// With `after(task1)` (or `after = task1`) the task runs after the named tasks of the
+/// block even if they share no data, e.g. for the order of log output. The named task may
+/// also come later in the block; a cycle is a compile error. Needs `name` or `run` on the
+/// block, because only the `PurtelTaskGraph` carries the dependencies to the executor.
+///
+/// Each declared parameter must be a variable in scope that is a shared handle
+/// (`purtel::PurtelSharedHandle`, e.g. `Arc<RwLock<T>>`); a misspelled parameter
+/// is a compile error.
+///
+/// On a function the parameter usage follows from the signature: `&mut T` is write,
+/// `&T` is read, other parameters are passed by value (cloned).
+/// ```ignore
+/// #[purtel_task]
+/// fn integrate(pos: &mut Positions, vel: &Velocities) { ... }
+/// ```
+/// Inside a purtel block `#[purtel_task] integrate(pos, vel);` registers a task that calls
+/// the function with the guards of the shared handles `pos` and `vel` (e.g.
+/// `Arc<RwLock<Positions>>`). The parameter identifiers are the names of the arguments.
+/// Their parameter usage is only known at runtime, hence such blocks need `name` or `run`
+/// and are planned at runtime.
 #[proc_macro_attribute]
-pub fn purtel_task(_args: TokenStream1, item: TokenStream1) -> TokenStream1 {
-    // we do nothing here; just a marker for the super macro
-    /*let attr_args = parse_macro_input!(args as AttributeArgs);
-    let attrs: PurtelTaskAttributes = PurtelTaskAttributes::from_list(&attr_args).expect("#[purtel_task] could not be parsed!");
-    println!("read_params: {:#?}", attrs.read_params());
-    println!("write_params: {:#?}", attrs.write_params());*/
+pub fn purtel_task(args: TokenStream1, item: TokenStream1) -> TokenStream1 {
+    // on a function we generate the companion, see `function.rs`
+    if let Ok(function) = syn::parse::<ItemFn>(item.clone()) {
+        let attr_args = parse_macro_input!(args as AttributeArgs);
+        let attrs = match PurtelTaskFnAttributes::from_list(&attr_args) {
+            Ok(attrs) => attrs,
+            Err(err) => return err.write_errors().into(),
+        };
+        return expand_task_fn(&attrs, function)
+            .unwrap_or_else(|err| err.to_compile_error())
+            .into();
+    }
+    // otherwise we do nothing here; just a marker for the super macro
     item
 }
+
+/// Turns every field of a state struct into an independent purtel parameter. For
+/// `struct World` it generates `WorldShared` with an `Arc<PurtelShared<T>>` per field,
+/// `World::into_shared(name)` and `WorldShared::into_inner()`. The fields get the
+/// identifiers `"state.positions"`, `"state.velocities"`, ..., so tasks that use different
+/// fields don't depend on each other.
+/// ```ignore
+/// let state = world.into_shared("state");
+/// ...
+/// #[purtel_task(write(state.positions), read(state.velocities))]
+/// let task = move || { ... };
+/// ```
+/// The name passed to `into_shared()` must be the name of the variable: with
+/// `world.into_shared("world")` the tasks above wouldn't depend on any other task.
+/// Debug builds panic if a declared parameter like `state.positions` doesn't match.
+///
+/// The path to the purtel types can be set with `#[purtel(crate = "purtel_core")]`.
+#[proc_macro_derive(PurtelState, attributes(purtel))]
+pub fn derive_purtel_state(item: TokenStream1) -> TokenStream1 {
+    let input = parse_macro_input!(item as DeriveInput);
+    expand_purtel_state(input).unwrap_or_else(|err| err).into()
+}
@@ -0,0 +1,36 @@
+//! Codegen. Calculation of the schedule during macro expansion. Uses exactly the same
+//! dependency analysis as `PurtelExecutor::calc_and_verify_exe_order()` in purtel-core.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Path;
+use purtel_core::{PurtelError, PurtelExecutor, TaskId};
+use crate::data::PurtelTaskParams;
+
+/// Calculates dependencies and execution levels of all tasks and returns a constant
+/// expression that constructs the `PurtelStaticSchedule`. `krate` is the path to the
+/// purtel types, `dependencies` are the explicit dependencies `(task, after)` from
+/// `#[purtel_task(after(...))]`. Fails if they form a cycle.
+pub fn static_schedule(krate: &Path,
+                       attributes: &[PurtelTaskParams],
+                       dependencies: &[(TaskId, TaskId)]) -> Result<TokenStream, PurtelError> {
+    // same order as the generated param usages: first write, read, reduce, then exclusive
+    let param_usages = attributes.iter()
+        .map(|attrs| {
+            attrs.write.iter().chain(&attrs.read).chain(&attrs.reduce).chain(&attrs.exclusive).cloned().collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut task_deps = PurtelExecutor::calc_task_dependencies(&param_usages);
+    PurtelExecutor::add_explicit_dependencies(&mut task_deps, dependencies)?;
+    let exe_order = PurtelExecutor::calc_execution_levels(task_deps.clone());
+
+    let task_deps = task_deps.iter().map(|deps| quote! { &[#(#deps),*] });
+    let exe_order = exe_order.iter().map(|level| quote! { &[#(#level),*] });
+    Ok(quote! {
+        #krate::PurtelStaticSchedule::new(
+            &[#(#task_deps),*],
+            &[#(#exe_order),*],
+        )
+    })
+}
@@ -0,0 +1,116 @@
+//! Codegen. `#[derive(PurtelState)]`: every field of a state struct becomes an independent
+//! purtel parameter.
+//! ```ignore
+//! #[derive(PurtelState)]
+//! struct World { positions: Vec<f64>, velocities: Vec<f64> }
+//! ```
+//! generates
+//! ```ignore
+//! struct WorldShared {
+//!     positions: Arc<purtel::PurtelShared<Vec<f64>>>,
+//!     velocities: Arc<purtel::PurtelShared<Vec<f64>>>,
+//! }
+//! impl World { fn into_shared(self, name: &str) -> WorldShared { ... } }
+//! impl WorldShared { fn into_inner(self) -> World { ... } }
+//! impl Clone for WorldShared { ... }
+//! ```
+//! With `let state = world.into_shared("state");` the identifier of each field is
+//! `"state.positions"` etc., the same as in `#[purtel_task(read(state.positions))]`.
+//! The name is a runtime value; for the declared parameters `#[purtel_tasks]` generates
+//! a check that the names match (`purtel_check_declared_identifier()`, debug builds).
+
+use darling::FromMeta;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, Meta, NestedMeta, Path, parse_quote};
+
+/// Attributes of `#[purtel(...)]` on the state struct.
+#[derive(Debug, Default, FromMeta)]
+struct PurtelStateAttributes {
+    /// `#[purtel(crate = "purtel_core")]`: path to the crate with the purtel types
+    #[darling(default, rename = "crate")]
+    krate: Option<Path>,
+}
+
+/// Generates the shared version of the struct, see above.
+pub fn expand_purtel_state(input: DeriveInput) -> Result<TokenStream, TokenStream> {
+    let mut attrs = PurtelStateAttributes::default();
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("purtel")) {
+        let attr_args = match attr.parse_meta().map_err(|err| err.to_compile_error())? {
+            Meta::List(list) => list.nested.into_iter().collect::<Vec<NestedMeta>>(),
+            meta => return Err(Error::new_spanned(meta, "expected `#[purtel(...)]`").to_compile_error()),
+        };
+        attrs = PurtelStateAttributes::from_list(&attr_args).map_err(|err| err.write_errors())?;
+    }
+    let krate = attrs.krate.unwrap_or_else(|| parse_quote!(purtel));
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(
+                &input.ident,
+                "#[derive(PurtelState)] needs a struct with named fields",
+            ).to_compile_error()),
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "#[derive(PurtelState)] needs a struct").to_compile_error()),
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let shared = format_ident!("{}Shared", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let field_vis = fields.iter().map(|field| &field.vis);
+    let field_names = fields.iter().map(|field| field.ident.as_ref().unwrap()).collect::<Vec<_>>();
+    let field_types = fields.iter().map(|field| &field.ty);
+    let field_strs = field_names.iter().map(|field| field.to_string());
+    let shared_doc = format!(
+        "Shared version of `{}`: every field is an independent purtel parameter. See `{}::into_shared()`.",
+        name, name,
+    );
+
+    Ok(quote! {
+        #[doc = #shared_doc]
+        #vis struct #shared #impl_generics #where_clause {
+            #(#field_vis #field_names: ::std::sync::Arc<#krate::PurtelShared<#field_types>>,)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Wraps every field in a `PurtelShared`. The identifier of each field is
+            /// `"<name>.<field>"`; `name` must be the name of the variable that tasks use,
+            /// because the identifiers of their parameters follow from it. With
+            /// `let state = world.into_shared("world");` a task that uses `state.positions`
+            /// would conflict with nothing. Debug builds check declared parameters like
+            /// `read(state.positions)`, inferred accesses are detected by
+            /// `PurtelAccessCheckMode::ENFORCE`.
+            #vis fn into_shared(self, name: &str) -> #shared #ty_generics {
+                #shared {
+                    #(#field_names: ::std::sync::Arc::new(#krate::PurtelShared::new(
+                        &format!("{}.{}", name, #field_strs),
+                        self.#field_names,
+                    )),)*
+                }
+            }
+        }
+
+        impl #impl_generics #shared #ty_generics #where_clause {
+            /// Returns the state. Panics if a field is still shared, e.g. by a task.
+            #vis fn into_inner(self) -> #name #ty_generics {
+                #name {
+                    #(#field_names: ::std::sync::Arc::try_unwrap(self.#field_names)
+                        .ok()
+                        .expect("state must not be shared anymore")
+                        .into_inner(),)*
+                }
+            }
+        }
+
+        // not derived: all fields are `Arc`s, the fields don't need to be `Clone`
+        impl #impl_generics ::std::clone::Clone for #shared #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                #shared {
+                    #(#field_names: ::std::sync::Arc::clone(&self.#field_names),)*
+                }
+            }
+        }
+    })
+}
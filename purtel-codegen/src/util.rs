@@ -2,6 +2,7 @@
 
 use proc_macro2::{TokenStream, Delimiter};
 use proc_macro2::TokenTree;
+use syn::Error;
 
 /// Prints a token tree recursively for debugging purpose.
 /// Also useful to understand how to traverse a token tree.
@@ -28,7 +29,7 @@ pub fn print_expanded_token_stream(stream: &TokenStream) {
 
 /// Unwraps a {}-code-block as it wasn't there in the first place.
 /// This is useful if you want to achieve the following:
-/// ```
+/// ```ignore
 /// // do_something_with_vars_proc_macro-proc macro unwraps the block after gaining some
 /// // meta info; in the end it looks like the {}-block was never there and only the inner
 /// // block remains there.
@@ -37,24 +38,18 @@ pub fn print_expanded_token_stream(stream: &TokenStream) {
 /// }
 /// let b = a;
 /// ```
-pub fn unwrap_block(stream: TokenStream) -> TokenStream {
-    let mut group = None;
-    for token_tree in stream {
-        match token_tree {
-            TokenTree::Group(ref gr) => { group = Some(gr.clone())}
-            _ => {}
+/// Returns an error spanned at the input if it isn't a single {}-block.
+pub fn unwrap_block(stream: TokenStream) -> syn::Result<TokenStream> {
+    let mut token_trees = stream.clone().into_iter().collect::<Vec<_>>();
+    // the attributed statement may include its semicolon
+    if let Some(TokenTree::Punct(punct)) = token_trees.last() {
+        if punct.as_char() == ';' {
+            token_trees.pop();
         }
     }
-
-    if group.is_none() {
-        panic!("No group found! #[purtel_tasks] must be around {{}} block");
+    match token_trees.as_slice() {
+        // we return the stream to the inner block; i.e. skip the surrounding block/group
+        [TokenTree::Group(group)] if group.delimiter() == Delimiter::Brace => Ok(group.stream()),
+        _ => Err(Error::new_spanned(stream, "#[purtel_tasks] must be attached to a {}-block")),
     }
-
-    let group = group.unwrap();
-    if group.delimiter() != Delimiter::Brace {
-        panic!("unwrap_block() only works for {{}}-blocks!");
-    }
-
-    // we return the stream to the inner block; i.e. skip the surrounding block/group
-    group.stream()
 }
@@ -0,0 +1,166 @@
+//! Runtime detection of parameter accesses that exceed the declared parameter usage of a task.
+
+use std::fmt;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::{PurtelParamUsage, PurtelParamUsageKind};
+use crate::PurtelParamUsageKind::{EXCLUSIVE, READ, WRITE};
+use crate::context::with_current_task;
+use crate::types::TaskId;
+
+/// Describes if and how `PurtelExecutor` checks the accesses that instrumented shared
+/// data types (e.g. `PurtelShared`) report during execution.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PurtelAccessCheckMode {
+    /// Accesses are neither recorded nor checked. Default.
+    OFF,
+    /// Accesses are recorded and returned in the `PurtelExecutionReport`.
+    RECORD,
+    /// Like `RECORD` but the execution fails with `PurtelError::UndeclaredAccess`
+    /// after the first execution level that contains an access that wasn't declared.
+    ENFORCE,
+}
+
+/// Returns the declared kind of the access to `identifier`: the join of the kinds of all
+/// declared usages of the identifier or one of its ancestors (e.g. `world` for
+/// `world.entities`). `None` if it wasn't declared.
+pub(crate) fn declared_kind(param_usages: &[PurtelParamUsage], identifier: &str) -> Option<PurtelParamUsageKind> {
+    param_usages.iter()
+        .filter(|p| p.covers(identifier))
+        .map(|p| p.kind())
+        .fold(None, |joined, kind| match joined {
+            Some(joined) => Some(kind.join(joined)),
+            None => Some(kind),
+        })
+}
+
+/// A reported access of a task to a parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurtelAccess {
+    task_id: TaskId,
+    identifier: String,
+    kind: PurtelParamUsageKind,
+}
+
+impl PurtelAccess {
+    pub(crate) fn new(task_id: TaskId, identifier: &str, kind: PurtelParamUsageKind) -> Self {
+        Self {
+            task_id,
+            identifier: identifier.to_owned(),
+            kind,
+        }
+    }
+
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn kind(&self) -> PurtelParamUsageKind {
+        self.kind
+    }
+}
+
+/// An access that exceeds the declared parameter usage of the task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurtelAccessViolation {
+    access: PurtelAccess,
+    /// What the task declared for the parameter; `None` if it didn't declare it at all.
+    declared: Option<PurtelParamUsageKind>,
+}
+
+impl PurtelAccessViolation {
+    /// Returns a violation if `access` exceeds what the task declared in `declared`.
+    /// `WRITE` and `EXCLUSIVE` allow every access, `READ` and `REDUCE` only themselves.
+    pub(crate) fn check(access: &PurtelAccess, declared: Option<PurtelParamUsageKind>) -> Option<Self> {
+        let exceeded = !matches!(declared, Some(WRITE) | Some(EXCLUSIVE)) && declared != Some(access.kind());
+        if exceeded {
+            Some(Self {
+                access: access.clone(),
+                declared,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn access(&self) -> &PurtelAccess {
+        &self.access
+    }
+
+    pub fn declared(&self) -> Option<PurtelParamUsageKind> {
+        self.declared
+    }
+}
+
+impl fmt::Display for PurtelAccessViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = &self.access;
+        match self.declared {
+            None => write!(
+                f, "task {} accessed parameter '{}' as {:?} but didn't declare it",
+                access.task_id(), access.identifier(), access.kind()
+            ),
+            Some(declared) => write!(
+                f, "task {} accessed parameter '{}' as {:?} but only declared {:?}",
+                access.task_id(), access.identifier(), access.kind(), declared
+            ),
+        }
+    }
+}
+
+/// Instrumented alternative to `RwLock<T>` for shared data of purtel tasks. Every access
+/// is reported to the executor together with the ID of the running task and the identifier
+/// of this parameter. Combined with `PurtelAccessCheckMode::ENFORCE` the executor detects
+/// tasks that touch data they did not declare in their `PurtelParamUsage` list.
+///
+/// Use it like `Arc<RwLock<T>>`: `Arc::new(PurtelShared::new("data1", vec![1, 2, 3]))`.
+#[derive(Debug)]
+pub struct PurtelShared<T> {
+    identifier: String,
+    lock: RwLock<T>,
+}
+
+impl<T> PurtelShared<T> {
+
+    /// Constructor. `identifier` must match the identifier that tasks use in their
+    /// `PurtelParamUsage` for this data.
+    pub fn new(identifier: &str, value: T) -> Self {
+        Self {
+            identifier: identifier.to_owned(),
+            lock: RwLock::new(value),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Shared access. Reports a `READ` access of the running task. Blocks like
+    /// `RwLock::read()`, so that an undeclared access ends up in a report
+    /// instead of a panic.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.report(READ);
+        self.lock.read().expect("PurtelShared lock is poisoned!")
+    }
+
+    /// Exclusive access. Reports a `WRITE` access of the running task.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.report(WRITE);
+        self.lock.write().expect("PurtelShared lock is poisoned!")
+    }
+
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner().expect("PurtelShared lock is poisoned!")
+    }
+
+    fn report(&self, kind: PurtelParamUsageKind) {
+        with_current_task(|ctx| {
+            if let Some(ctx) = ctx {
+                ctx.record_access(&self.identifier, kind, self as *const Self as *const ());
+            }
+        });
+    }
+}
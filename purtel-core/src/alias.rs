@@ -0,0 +1,115 @@
+//! Alias groups. Several identifiers can refer to the same data, e.g. a field and a local
+//! clone of the same `Arc<RwLock<T>>`. The executor maps all identifiers of a group to one
+//! canonical identifier before it calculates the dependencies.
+
+use std::collections::HashMap;
+use crate::{PurtelParamUsage, PurtelSharedHandle};
+use crate::PurtelParamUsageKind::WRITE;
+
+/// Alias groups of parameter identifiers. Aliases are either declared explicitly with
+/// `alias()` or detected by registering the shared handles with `register()`
+/// (registry-based mode): handles that point to the same data are aliases. Aliases conflict
+/// with each other in the dependency analysis and cover each other in the access checks. In the
+/// registry-based mode `PurtelExecutor::execute()` fails with `PurtelError::UnregisteredHandle`
+/// if a task accesses instrumented shared data (`PurtelShared`, `PurtelReduction`,
+/// `PurtelVersioned`) that wasn't registered, because its aliases would be missed.
+#[derive(Debug, Default, Clone)]
+pub struct PurtelAliases {
+    /// union-find forest; identifier => parent identifier
+    parents: HashMap<String, String>,
+    /// address of the shared data => first identifier it was registered with
+    handles: HashMap<usize, String>,
+}
+
+impl PurtelAliases {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares that `a` and `b` refer to the same data. Children are aliases as well,
+    /// e.g. `a.x` and `b.x`.
+    pub fn alias(&mut self, a: &str, b: &str) {
+        let a = self.find(a);
+        let b = self.find(b);
+        // the smaller identifier becomes the representative; this makes it deterministic
+        if a < b {
+            self.parents.insert(b, a);
+        } else if b < a {
+            self.parents.insert(a, b);
+        }
+    }
+
+    /// Registers the shared handle of a parameter. If the same data (by `Arc` pointer
+    /// identity) was already registered with another identifier, both are aliases.
+    pub fn register<H: PurtelSharedHandle>(&mut self, identifier: &str, handle: &H) {
+        let address = handle.data_ptr() as usize;
+        match self.handles.get(&address).cloned() {
+            Some(known) => self.alias(&known, identifier),
+            None => {
+                self.handles.insert(address, identifier.to_owned());
+            }
+        }
+    }
+
+    /// True in the registry-based mode, i.e. if at least one handle was registered.
+    pub fn is_registry(&self) -> bool {
+        !self.handles.is_empty()
+    }
+
+    /// True if the data at the given address (see `PurtelSharedHandle::data_ptr()`)
+    /// was registered.
+    pub(crate) fn is_registered(&self, data: *const ()) -> bool {
+        self.handles.contains_key(&(data as usize))
+    }
+
+    /// Returns the canonical identifier: the representative of the alias group of the
+    /// identifier or, for children, of the alias group of its closest aliased ancestor.
+    pub fn canonical(&self, identifier: &str) -> String {
+        let mut identifier = identifier.to_owned();
+        // every step replaces a prefix by its representative; the bound prevents endless
+        // loops for groups like `a` and `a.x`
+        for _ in 0..=self.parents.len() {
+            let mut ancestors = self.parents.keys()
+                .chain(self.parents.values())
+                .filter(|key| PurtelParamUsage::new(key, WRITE).covers(&identifier))
+                .collect::<Vec<_>>();
+            // closest ancestor first
+            ancestors.sort_by_key(|key| std::cmp::Reverse(key.len()));
+            let replacement = ancestors.iter()
+                .map(|key| (key, self.find(key)))
+                .find(|(key, representative)| **key != representative);
+            match replacement {
+                Some((key, representative)) => identifier = format!("{}{}", representative, &identifier[key.len()..]),
+                None => break,
+            }
+        }
+        identifier
+    }
+
+    /// Maps the usages of a task to canonical identifiers. Two usages of the same data
+    /// (and the same range) become one with the join of both kinds.
+    pub fn canonical_usages(&self, param_usages: &[PurtelParamUsage]) -> Vec<PurtelParamUsage> {
+        let mut canonical: Vec<PurtelParamUsage> = vec![];
+        for usage in param_usages {
+            let usage = PurtelParamUsage {
+                identifier: self.canonical(usage.identifier()),
+                ..usage.clone()
+            };
+            match canonical.iter_mut().find(|u| u.identifier == usage.identifier && u.range == usage.range) {
+                Some(known) => known.kind = known.kind.join(usage.kind),
+                None => canonical.push(usage),
+            }
+        }
+        canonical
+    }
+
+    /// Representative of the alias group of exactly this identifier.
+    fn find(&self, identifier: &str) -> String {
+        let mut identifier = identifier;
+        while let Some(parent) = self.parents.get(identifier) {
+            identifier = parent;
+        }
+        identifier.to_owned()
+    }
+}
@@ -0,0 +1,176 @@
+//! Shared data cell whose access is validated by the schedule of `PurtelExecutor`
+//! instead of a lock.
+
+use std::cell::UnsafeCell;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicIsize, Ordering};
+#[cfg(debug_assertions)]
+use crate::context::with_current_task;
+#[cfg(debug_assertions)]
+use crate::PurtelParamUsageKind::{EXCLUSIVE, REDUCE, WRITE};
+
+/// Value of the borrow counter if a writer holds the cell.
+const WRITER: isize = -1;
+
+/// Lock-free alternative to `RwLock<T>` for data that is only accessed by purtel tasks.
+/// `PurtelExecutor::calc_and_verify_exe_order()` already guarantees that no two tasks
+/// with a conflicting `PurtelParamUsageKind` for the same parameter run at the same time.
+/// Hence a task can get `&T` or `&mut T` without taking a lock, as long as it declared
+/// the parameter with the identifier of the cell.
+///
+/// Every access checks an atomic borrow counter (like `RefCell`, but thread-safe): a
+/// conflicting access at the same time panics instead of causing a data race, also in
+/// release builds. No access waits. Debug builds additionally check that the running task
+/// declared the identifier (`WRITE` for `write()`).
+///
+/// Use it like `Arc<RwLock<T>>`: `Arc::new(PurtelCell::new("data1", vec![1, 2, 3]))`.
+/// An access always borrows the whole data, even if the task only declared a range
+/// (`data1[0..1024]`); for partitioned data use `PurtelSlice`.
+pub struct PurtelCell<T> {
+    identifier: String,
+    value: UnsafeCell<T>,
+    /// `n > 0`: n readers; `0`: unused; `-1`: one writer
+    borrow: AtomicIsize,
+}
+
+// Access is synchronized by the execution levels of the executor; `borrow` turns
+// a violation of the schedule into a panic.
+unsafe impl<T: Send> Send for PurtelCell<T> {}
+unsafe impl<T: Send + Sync> Sync for PurtelCell<T> {}
+
+impl<T> PurtelCell<T> {
+
+    /// Constructor. `identifier` must match the identifier that tasks use in their
+    /// `PurtelParamUsage` for this data.
+    pub fn new(identifier: &str, value: T) -> Self {
+        Self {
+            identifier: identifier.to_owned(),
+            value: UnsafeCell::new(value),
+            borrow: AtomicIsize::new(0),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Shared access. The running task must have declared the identifier as `READ` or `WRITE`.
+    pub fn read(&self) -> PurtelCellRef<'_, T> {
+        #[cfg(debug_assertions)]
+        self.check_declaration(false);
+        // only increment if there is no writer; a blind increment would briefly free the cell
+        // of a writer for another writer
+        let mut prev = self.borrow.load(Ordering::Relaxed);
+        loop {
+            if prev < 0 {
+                panic!("PurtelCell '{}' is read while it is written! The schedule was violated.", self.identifier);
+            }
+            match self.borrow.compare_exchange_weak(prev, prev + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => prev = current,
+            }
+        }
+        // the counter guarantees that there is no writer until the reference is dropped
+        PurtelCellRef {
+            cell: self,
+            value: unsafe { &*self.value.get() },
+        }
+    }
+
+    /// Exclusive access. The running task must have declared the identifier as `WRITE` or `EXCLUSIVE`.
+    pub fn write(&self) -> PurtelCellRefMut<'_, T> {
+        #[cfg(debug_assertions)]
+        self.check_declaration(true);
+        if self.borrow.compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            panic!("PurtelCell '{}' is written while it is accessed by another task! The schedule was violated.", self.identifier);
+        }
+        // the counter guarantees that there is no other reference until this one is dropped
+        PurtelCellRefMut {
+            cell: self,
+            value: unsafe { &mut *self.value.get() },
+        }
+    }
+
+    /// Access without any runtime check. Only possible if nobody else can use the cell.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Panics if the task that runs on the current thread didn't declare the usage
+    /// of this cell. Accesses from outside of a purtel task (e.g. from the main thread
+    /// after the execution) are not checked.
+    #[cfg(debug_assertions)]
+    fn check_declaration(&self, write: bool) {
+        with_current_task(|ctx| {
+            if let Some(ctx) = ctx {
+                match ctx.declared_kind(&self.identifier) {
+                    None => panic!(
+                        "Task {} accesses PurtelCell '{}' but didn't declare it!",
+                        ctx.task_id(), self.identifier
+                    ),
+                    Some(kind) if write && kind != WRITE && kind != EXCLUSIVE => panic!(
+                        "Task {} writes PurtelCell '{}' but only declared {:?}!",
+                        ctx.task_id(), self.identifier, kind
+                    ),
+                    // other reducers of the cell may run at the same time
+                    Some(REDUCE) => panic!(
+                        "Task {} reads PurtelCell '{}' but only declared REDUCE!",
+                        ctx.task_id(), self.identifier
+                    ),
+                    _ => {}
+                }
+            }
+        });
+    }
+}
+
+/// Shared reference to the data of a `PurtelCell`. Returned by `PurtelCell::read()`.
+pub struct PurtelCellRef<'a, T> {
+    cell: &'a PurtelCell<T>,
+    value: &'a T,
+}
+
+impl<T> Deref for PurtelCellRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> Drop for PurtelCellRef<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Exclusive reference to the data of a `PurtelCell`. Returned by `PurtelCell::write()`.
+pub struct PurtelCellRefMut<'a, T> {
+    cell: &'a PurtelCell<T>,
+    value: &'a mut T,
+}
+
+impl<T> Deref for PurtelCellRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for PurtelCellRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T> Drop for PurtelCellRefMut<'_, T> {
+    fn drop(&mut self) {
+        let released = self.cell.borrow.compare_exchange(WRITER, 0, Ordering::Release, Ordering::Relaxed);
+        debug_assert!(released.is_ok(), "PurtelCell writer must hold the cell");
+    }
+}
@@ -0,0 +1,169 @@
+//! Thread-local bookkeeping about the task that currently runs on a thread. The executor
+//! installs the context right before it invokes the closure of a task. Shared data types
+//! of purtel use it to validate an access against the declared parameter usage of the task
+//! and to report accesses back to the executor.
+
+use std::cell::RefCell;
+#[cfg(debug_assertions)]
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::{PurtelParamUsage, PurtelParamUsageKind};
+use crate::access::{declared_kind, PurtelAccess};
+use crate::alias::PurtelAliases;
+use crate::types::TaskId;
+
+thread_local! {
+    /// Context of the task that currently runs on this thread. `None` if the thread
+    /// doesn't execute a purtel task right now (e.g. the main thread before/after execution).
+    static CURRENT_TASK: RefCell<Option<PurtelTaskContext>> = const { RefCell::new(None) };
+}
+
+/// Source of the IDs that distinguish the executions of different executors.
+static NEXT_EXECUTION_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn next_execution_id() -> u64 {
+    NEXT_EXECUTION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Everything a shared data type needs to know about the currently running task.
+#[derive(Debug, Clone)]
+pub(crate) struct PurtelTaskContext {
+    task_id: TaskId,
+    /// distinguishes the executions of different executors, see `PurtelReduction` and
+    /// `PurtelVersioned`
+    execution_id: u64,
+    // only inspected by the debug-mode checks of `PurtelCell`
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    param_usages: Vec<PurtelParamUsage>,
+    /// alias groups; `param_usages` already use the canonical identifiers
+    aliases: Arc<PurtelAliases>,
+    /// canonical identifiers of the renamed parameters, see `PurtelExecutor::rename()`
+    renamed: Vec<String>,
+    /// Accesses reported by instrumented shared data types. `None` if the executor
+    /// doesn't record accesses.
+    accesses: Option<RefCell<Vec<PurtelAccess>>>,
+    /// Accesses to data that wasn't registered in the registry-based alias mode.
+    unregistered: RefCell<Vec<PurtelAccess>>,
+}
+
+/// What a task reported while it ran; returned by `run_in_context()`.
+#[derive(Debug, Default)]
+pub(crate) struct PurtelTaskRecord {
+    /// the recorded accesses; empty if the executor doesn't record them
+    pub(crate) accesses: Vec<PurtelAccess>,
+    /// accesses to data that wasn't registered, see `PurtelAliases::register()`
+    pub(crate) unregistered: Vec<PurtelAccess>,
+}
+
+impl PurtelTaskRecord {
+    pub(crate) fn extend(&mut self, other: PurtelTaskRecord) {
+        self.accesses.extend(other.accesses);
+        self.unregistered.extend(other.unregistered);
+    }
+}
+
+impl PurtelTaskContext {
+    pub(crate) fn new(task_id: TaskId,
+                      execution_id: u64,
+                      param_usages: Vec<PurtelParamUsage>,
+                      aliases: Arc<PurtelAliases>,
+                      renamed: Vec<String>,
+                      record_accesses: bool) -> Self {
+        Self {
+            task_id,
+            execution_id,
+            param_usages,
+            aliases,
+            renamed,
+            accesses: if record_accesses { Some(RefCell::new(vec![])) } else { None },
+            unregistered: RefCell::new(vec![]),
+        }
+    }
+
+    pub(crate) fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    pub(crate) fn execution_id(&self) -> u64 {
+        self.execution_id
+    }
+
+    /// Returns the declared usage kind for the given parameter identifier (or one of its
+    /// ancestors or aliases) or `None` if the task didn't declare the parameter at all.
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    pub(crate) fn declared_kind(&self, identifier: &str) -> Option<PurtelParamUsageKind> {
+        declared_kind(&self.param_usages, &self.aliases.canonical(identifier))
+    }
+
+    /// Like `declared_kind()` for an access to the indices `range` of the parameter: only
+    /// usages of the whole parameter or of a range that contains `range` count.
+    #[cfg(debug_assertions)]
+    pub(crate) fn declared_range_kind(&self, identifier: &str, range: &Range<usize>) -> Option<PurtelParamUsageKind> {
+        let usages = self.param_usages.iter()
+            .filter(|p| p.range().is_none_or(|declared| declared.start <= range.start && range.end <= declared.end))
+            .cloned()
+            .collect::<Vec<_>>();
+        declared_kind(&usages, &self.aliases.canonical(identifier))
+    }
+
+    /// True if the parameter with the given identifier (or one of its ancestors or aliases)
+    /// is renamed.
+    pub(crate) fn is_renamed(&self, identifier: &str) -> bool {
+        let canonical = self.aliases.canonical(identifier);
+        self.renamed.iter()
+            .any(|renamed| PurtelParamUsage::new(renamed, PurtelParamUsageKind::WRITE).covers(&canonical))
+    }
+
+    /// Records that the task accessed the parameter with the given identifier; `data` is the
+    /// address of the shared data. Every combination of identifier and kind is only recorded
+    /// once per task.
+    pub(crate) fn record_access(&self, identifier: &str, kind: PurtelParamUsageKind, data: *const ()) {
+        if let Some(accesses) = &self.accesses {
+            Self::push_once(&mut accesses.borrow_mut(), self.task_id, identifier, kind);
+        }
+        if self.aliases.is_registry() && !self.aliases.is_registered(data) {
+            Self::push_once(&mut self.unregistered.borrow_mut(), self.task_id, identifier, kind);
+        }
+    }
+
+    fn push_once(accesses: &mut Vec<PurtelAccess>, task_id: TaskId, identifier: &str, kind: PurtelParamUsageKind) {
+        let known = accesses.iter()
+            .any(|a| a.identifier() == identifier && a.kind() == kind);
+        if !known {
+            accesses.push(PurtelAccess::new(task_id, identifier, kind));
+        }
+    }
+}
+
+/// Resets the thread-local context when dropped. This way the context is also
+/// removed if the task panics.
+struct ContextGuard;
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CURRENT_TASK.with(|ctx| ctx.borrow_mut().take());
+    }
+}
+
+/// Runs the closure of a task with the given context installed on the current thread.
+/// Returns all accesses that were reported during the execution of the task.
+pub(crate) fn run_in_context<F: FnOnce()>(context: PurtelTaskContext, task: F) -> PurtelTaskRecord {
+    CURRENT_TASK.with(|ctx| {
+        let prev = ctx.borrow_mut().replace(context);
+        debug_assert!(prev.is_none(), "Tasks can't be nested on the same thread!");
+    });
+    let _guard = ContextGuard;
+    task();
+    CURRENT_TASK.with(|ctx| ctx.borrow_mut().take())
+        .map(|ctx| PurtelTaskRecord {
+            accesses: ctx.accesses.map(|accesses| accesses.into_inner()).unwrap_or_default(),
+            unregistered: ctx.unregistered.into_inner(),
+        })
+        .unwrap_or_default()
+}
+
+/// Gives access to the context of the task that runs on the current thread.
+pub(crate) fn with_current_task<R, F: FnOnce(Option<&PurtelTaskContext>) -> R>(f: F) -> R {
+    CURRENT_TASK.with(|ctx| f(ctx.borrow().as_ref()))
+}
@@ -0,0 +1,131 @@
+//! Differential check: a graph must produce the same shared data when it runs in parallel
+//! as when it runs sequentially. Turns the correctness guarantee of the task model into
+//! something a test suite can verify.
+
+use std::sync::{Arc, RwLock};
+use crate::{PurtelExecutor, PurtelExecutionMode, PurtelError};
+use crate::access::PurtelShared;
+use crate::cell::PurtelCell;
+use crate::reduce::PurtelReduction;
+use crate::versioned::PurtelVersioned;
+use crate::slice::PurtelSlice;
+
+/// The complete shared data of a graph. Implemented by the user, usually with the
+/// help of `PurtelDiffParam` for each parameter.
+pub trait PurtelDiffState {
+    /// Copies the data behind all parameters. Cloning an `Arc` is not enough because
+    /// both runs would work on the same data.
+    fn deep_clone(&self) -> Self;
+
+    /// Identifiers of all parameters. Parameters are compared in this order.
+    fn param_identifiers(&self) -> Vec<String>;
+
+    /// Compares the value of one parameter with the value in another state.
+    fn param_eq(&self, other: &Self, identifier: &str) -> bool;
+}
+
+/// A single shared parameter that can be copied and compared. Implemented for all
+/// shared data types supported by purtel.
+pub trait PurtelDiffParam {
+    fn deep_clone(&self) -> Self;
+
+    fn value_eq(&self, other: &Self) -> bool;
+}
+
+impl<T: Clone + PartialEq> PurtelDiffParam for Arc<RwLock<T>> {
+    fn deep_clone(&self) -> Self {
+        Arc::new(RwLock::new(self.read().unwrap().clone()))
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        *self.read().unwrap() == *other.read().unwrap()
+    }
+}
+
+impl<T: Clone + PartialEq> PurtelDiffParam for Arc<PurtelShared<T>> {
+    fn deep_clone(&self) -> Self {
+        Arc::new(PurtelShared::new(self.identifier(), self.read().clone()))
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        *self.read() == *other.read()
+    }
+}
+
+impl<T: Clone + PartialEq> PurtelDiffParam for Arc<PurtelCell<T>> {
+    fn deep_clone(&self) -> Self {
+        Arc::new(PurtelCell::new(self.identifier(), self.read().clone()))
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        *self.read() == *other.read()
+    }
+}
+
+impl<T: Clone + PartialEq> PurtelDiffParam for Arc<PurtelSlice<T>> {
+    fn deep_clone(&self) -> Self {
+        Arc::new(PurtelSlice::new(self.identifier(), self.read(0..self.len()).to_vec()))
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        *self.read(0..self.len()) == *other.read(0..other.len())
+    }
+}
+
+impl<T: Clone + PartialEq> PurtelDiffParam for Arc<PurtelReduction<T>> {
+    fn deep_clone(&self) -> Self {
+        Arc::new(self.with_value(self.read().clone()))
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        *self.read() == *other.read()
+    }
+}
+
+impl<T: Clone + PartialEq> PurtelDiffParam for Arc<PurtelVersioned<T>> {
+    fn deep_clone(&self) -> Self {
+        Arc::new(PurtelVersioned::new(self.identifier(), T::clone(&self.read())))
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        *self.read() == *other.read()
+    }
+}
+
+/// Like `purtel_differential_check_with_mode()` with `PurtelExecutionMode::PARALLEL`.
+pub fn purtel_differential_check<S, F>(initial_state: &S, build: F) -> Result<(), PurtelError>
+    where S: PurtelDiffState,
+          F: Fn(&S) -> PurtelExecutor {
+    purtel_differential_check_with_mode(initial_state, PurtelExecutionMode::PARALLEL, build)
+}
+
+/// Runs a graph once with `PurtelExecutionMode::SEQUENTIAL_BY_ID` and once with `mode`,
+/// each time on its own deep copy of `initial_state`. `build` constructs the executor for
+/// the given state; the harness plans and runs it. Fails with `PurtelError::StateMismatch`
+/// and the first parameter identifier whose final value differs.
+pub fn purtel_differential_check_with_mode<S, F>(initial_state: &S,
+                                                 mode: PurtelExecutionMode,
+                                                 build: F) -> Result<(), PurtelError>
+    where S: PurtelDiffState,
+          F: Fn(&S) -> PurtelExecutor {
+    let sequential_state = initial_state.deep_clone();
+    run(&sequential_state, PurtelExecutionMode::SEQUENTIAL_BY_ID, &build)?;
+
+    let parallel_state = initial_state.deep_clone();
+    run(&parallel_state, mode, &build)?;
+
+    let mismatch = sequential_state.param_identifiers().into_iter()
+        .find(|identifier| !sequential_state.param_eq(&parallel_state, identifier));
+    match mismatch {
+        Some(identifier) => Err(PurtelError::StateMismatch(identifier)),
+        None => Ok(()),
+    }
+}
+
+fn run<S, F>(state: &S, mode: PurtelExecutionMode, build: &F) -> Result<(), PurtelError>
+    where F: Fn(&S) -> PurtelExecutor {
+    let mut executor = build(state);
+    executor.set_execution_mode(mode);
+    executor.calc_and_verify_exe_order();
+    executor.execute().map(|_| ())
+}
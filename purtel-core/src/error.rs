@@ -0,0 +1,67 @@
+//! Errors that can occur during the execution of purtel tasks.
+
+use std::error::Error;
+use std::fmt;
+use crate::access::{PurtelAccess, PurtelAccessViolation};
+use crate::types::TaskId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PurtelError {
+    /// At least one task accessed a parameter beyond its declaration. Only detected
+    /// for instrumented shared data and with `PurtelAccessCheckMode::ENFORCE`.
+    UndeclaredAccess(Vec<PurtelAccessViolation>),
+    /// The differential check found a parameter (identifier) whose final value
+    /// differs between the sequential and the parallel execution.
+    StateMismatch(String),
+    /// The explicit dependencies (`PurtelExecutor::add_dependency()`) form a cycle together
+    /// with the derived ones. Each task of the cycle depends on the next one, the last
+    /// one on the first one.
+    DependencyCycle(Vec<TaskId>),
+    /// In the registry-based alias mode (`PurtelAliases::register()`) a task accessed
+    /// instrumented shared data that wasn't registered; its aliases can't be known.
+    UnregisteredHandle(Vec<PurtelAccess>),
+    /// The parameter (identifier) is renamed (`PurtelExecutor::rename()`), but the first task
+    /// runs after the second one, which has a higher ID. The versions are ordered by task
+    /// ID, hence the first task wouldn't see the version of the second one.
+    RenamingConflict(String, TaskId, TaskId),
+}
+
+impl fmt::Display for PurtelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurtelError::UndeclaredAccess(violations) => {
+                write!(f, "parameter usage declaration is incomplete, the schedule may be wrong:")?;
+                for violation in violations {
+                    write!(f, "\n  - {}", violation)?;
+                }
+                Ok(())
+            }
+            PurtelError::StateMismatch(identifier) => write!(
+                f, "parameter '{}' differs between the sequential and the parallel execution; \
+                    the parameter usage declaration is probably incomplete",
+                identifier
+            ),
+            PurtelError::DependencyCycle(cycle) => {
+                write!(f, "the task dependencies contain a cycle: task {}", cycle[0])?;
+                for task_id in cycle.iter().skip(1).chain(&cycle[..1]) {
+                    write!(f, " runs after task {}", task_id)?;
+                }
+                Ok(())
+            }
+            PurtelError::UnregisteredHandle(accesses) => {
+                write!(f, "shared data wasn't registered in the alias registry, aliases may be missed:")?;
+                for access in accesses {
+                    write!(f, "\n  - task {} accessed '{}'", access.task_id(), access.identifier())?;
+                }
+                Ok(())
+            }
+            PurtelError::RenamingConflict(identifier, task_id, later) => write!(
+                f, "task {} runs after task {} but the versions of the renamed parameter '{}' \
+                    are ordered by task ID",
+                task_id, later, identifier
+            ),
+        }
+    }
+}
+
+impl Error for PurtelError {}
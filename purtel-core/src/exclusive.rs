@@ -0,0 +1,82 @@
+//! Mutual exclusion of tasks with the parameter usage `EXCLUSIVE`. These tasks have no
+//! dependency on each other, hence they can end up in the same execution level (or run at the
+//! same time in mode `FUZZ`). The executor wraps each of them so that it holds a mutex of
+//! every parameter it declared as `EXCLUSIVE` while it runs. Whichever task gets the mutex
+//! first runs first.
+
+use std::sync::{Arc, Mutex};
+use crate::PurtelParamUsage;
+use crate::PurtelParamUsageKind::EXCLUSIVE;
+use crate::types::TaskId;
+
+/// The mutexes that each task must hold while it runs.
+#[derive(Debug, Default)]
+pub(crate) struct PurtelExclusionLocks {
+    /// task id => mutexes, ordered by their group; the fixed order prevents deadlocks
+    task_locks: Vec<Vec<Arc<Mutex<()>>>>,
+}
+
+impl PurtelExclusionLocks {
+
+    /// Groups all `EXCLUSIVE` usages that overlap (see `PurtelParamUsage::overlaps()`),
+    /// directly or transitively, and creates one mutex per group.
+    pub(crate) fn new(param_usages: &[Vec<PurtelParamUsage>]) -> Self {
+        let usages = param_usages.iter()
+            .enumerate()
+            .flat_map(|(task_id, usages)| usages.iter().map(move |usage| (task_id, usage)))
+            .filter(|(_, usage)| usage.kind() == EXCLUSIVE)
+            .collect::<Vec<_>>();
+
+        // union-find over the indices of `usages`
+        let mut groups = (0..usages.len()).collect::<Vec<_>>();
+        fn find(groups: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while groups[root] != root {
+                root = groups[root];
+            }
+            groups[i] = root;
+            root
+        }
+        for i in 0..usages.len() {
+            for j in (i + 1)..usages.len() {
+                if usages[i].1.overlaps(usages[j].1) {
+                    let (root_i, root_j) = (find(&mut groups, i), find(&mut groups, j));
+                    groups[root_i.max(root_j)] = root_i.min(root_j);
+                }
+            }
+        }
+
+        let mutexes = (0..usages.len()).map(|_| Arc::new(Mutex::new(()))).collect::<Vec<_>>();
+        let mut task_groups = vec![vec![]; param_usages.len()];
+        for (i, (task_id, _)) in usages.iter().enumerate() {
+            let group = find(&mut groups, i);
+            if !task_groups[*task_id].contains(&group) {
+                task_groups[*task_id].push(group);
+            }
+        }
+        let task_locks = task_groups.into_iter()
+            .map(|mut task_groups| {
+                task_groups.sort_unstable();
+                task_groups.into_iter().map(|group| mutexes[group].clone()).collect()
+            })
+            .collect();
+        Self {
+            task_locks,
+        }
+    }
+
+    /// Wraps the closure of a task so that it holds its mutexes while it runs.
+    pub(crate) fn wrap(&self, task_id: TaskId, task: Box<dyn FnOnce() + Send>) -> Box<dyn FnOnce() + Send> {
+        let locks = match self.task_locks.get(task_id) {
+            Some(locks) if !locks.is_empty() => locks.clone(),
+            _ => return task,
+        };
+        Box::new(move || {
+            // a panicking task poisons the mutex but the data is protected by its own lock
+            let _guards = locks.iter()
+                .map(|lock| lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+                .collect::<Vec<_>>();
+            task();
+        })
+    }
+}
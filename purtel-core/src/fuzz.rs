@@ -0,0 +1,126 @@
+//! Schedule fuzzing: executes the tasks in a random order that is still consistent
+//! with the dependencies. Incomplete parameter usage declarations become visible
+//! because the order of "independent" tasks changes from run to run.
+
+use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::types::{TaskDependencies, TaskId};
+
+/// Configuration of `PurtelExecutionMode::FUZZ`. The same seed always produces the same
+/// start order of the tasks and the same artificial delays, so failures are reproducible.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PurtelFuzzConfig {
+    seed: u64,
+    max_delay: Option<Duration>,
+}
+
+impl PurtelFuzzConfig {
+
+    /// Constructor. No artificial delays.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            max_delay: None,
+        }
+    }
+
+    /// Constructor with a seed derived from the current time. Useful in CI to
+    /// run the graph many times; the seed is part of the report.
+    pub fn random() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+            .expect("system time must be after the unix epoch")
+            .as_nanos();
+        Self::new(nanos as u64)
+    }
+
+    /// Each task is delayed by a random duration in `[0, max_delay]` before it starts.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn max_delay(&self) -> Option<Duration> {
+        self.max_delay
+    }
+}
+
+/// Small and fast pseudo random number generator ("SplitMix64"). Good enough
+/// for shuffling and we don't need an external dependency.
+pub(crate) struct FuzzRng {
+    state: u64,
+}
+
+impl FuzzRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `[0, bound)`. `bound` must not be zero.
+    pub(crate) fn next_below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Random duration in `[0, max]`.
+    pub(crate) fn next_delay(&mut self, max: Duration) -> Duration {
+        let max_nanos = max.as_nanos().min(u64::MAX as u128 - 1) as u64;
+        Duration::from_nanos(self.next_u64() % (max_nanos + 1))
+    }
+}
+
+/// Calculates a random topological order of all tasks: every task comes after all
+/// tasks it depends on. In each step a random task out of all ready tasks is chosen.
+pub(crate) fn random_topological_order(task_deps: &[TaskDependencies], rng: &mut FuzzRng) -> Vec<TaskId> {
+    let mut order = Vec::with_capacity(task_deps.len());
+    let mut placed = vec![false; task_deps.len()];
+
+    while order.len() < task_deps.len() {
+        let ready = (0..task_deps.len())
+            .filter(|task_id| !placed[*task_id])
+            .filter(|task_id| task_deps[*task_id].iter().all(|dep| placed[*dep]))
+            .collect::<Vec<TaskId>>();
+        assert!(!ready.is_empty(), "No task is ready! Deadlock or algorithm error?");
+
+        let task_id = ready[rng.next_below(ready.len())];
+        placed[task_id] = true;
+        order.push(task_id);
+    }
+
+    order
+}
+
+/// Notifies the executor when the thread of a task finishes. Sends on drop, so
+/// that the executor also wakes up if the task panicked.
+pub(crate) struct FinishNotifier {
+    task_id: TaskId,
+    sender: Sender<TaskId>,
+}
+
+impl FinishNotifier {
+    pub(crate) fn new(task_id: TaskId, sender: Sender<TaskId>) -> Self {
+        Self {
+            task_id,
+            sender,
+        }
+    }
+}
+
+impl Drop for FinishNotifier {
+    fn drop(&mut self) {
+        // the executor may already be gone if it panicked itself
+        let _ = self.sender.send(self.task_id);
+    }
+}
@@ -0,0 +1,93 @@
+//! Task graphs. Everything `#[purtel_tasks(name = "...")]` generates, bundled in a single
+//! binding: the tasks, their parameter usage and the schedule calculated at compile time.
+
+use crate::{PurtelExecutor, PurtelParamUsage, PurtelTask};
+use crate::schedule::PurtelStaticSchedule;
+use crate::types::TaskId;
+
+/// All tasks of a `#[purtel_tasks]`-block together with their parameter usage
+/// (same order) and their schedule. There is no schedule if the parameter usage
+/// wasn't known at compile time (tasks from `#[purtel_task]` functions).
+pub struct PurtelTaskGraph {
+    tasks: Vec<PurtelTask>,
+    param_usages: Vec<Vec<PurtelParamUsage>>,
+    schedule: Option<PurtelStaticSchedule>,
+    /// explicit dependencies `(task, after)` from `#[purtel_task(after(...))]`
+    dependencies: Vec<(TaskId, TaskId)>,
+}
+
+impl PurtelTaskGraph {
+
+    /// Constructor. Used by the generated code of `#[purtel_tasks]`.
+    pub fn new(tasks: Vec<PurtelTask>,
+               param_usages: Vec<Vec<PurtelParamUsage>>,
+               schedule: PurtelStaticSchedule) -> Self {
+        assert_eq!(tasks.len(), param_usages.len(), "You must specify param usage for every task!");
+        Self {
+            tasks,
+            param_usages,
+            schedule: Some(schedule),
+            dependencies: vec![],
+        }
+    }
+
+    /// Constructor. The schedule gets calculated at runtime by `into_executor()`.
+    pub fn without_schedule(tasks: Vec<PurtelTask>,
+                            param_usages: Vec<Vec<PurtelParamUsage>>) -> Self {
+        assert_eq!(tasks.len(), param_usages.len(), "You must specify param usage for every task!");
+        Self {
+            tasks,
+            param_usages,
+            schedule: None,
+            dependencies: vec![],
+        }
+    }
+
+    /// Adds explicit dependencies `(task, after)`, see `PurtelExecutor::add_dependency()`.
+    /// Used by the generated code of `#[purtel_tasks]`; the precomputed schedule must
+    /// already contain them.
+    pub fn with_dependencies(mut self, dependencies: Vec<(TaskId, TaskId)>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Number of tasks.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn param_usages(&self) -> &[Vec<PurtelParamUsage>] {
+        &self.param_usages
+    }
+
+    pub fn schedule(&self) -> Option<PurtelStaticSchedule> {
+        self.schedule
+    }
+
+    pub fn dependencies(&self) -> &[(TaskId, TaskId)] {
+        &self.dependencies
+    }
+
+    /// Creates an executor with the precomputed schedule, see `PurtelExecutor::with_schedule()`.
+    /// Without a precomputed schedule the executor plans at runtime.
+    pub fn into_executor(self) -> PurtelExecutor {
+        match self.schedule {
+            Some(schedule) => {
+                let mut executor = PurtelExecutor::with_schedule(self.tasks, self.param_usages, &schedule);
+                // keeps them if the schedule gets replaced, e.g. by `set_aliases()`
+                executor.set_explicit_dependencies(self.dependencies);
+                executor
+            },
+            None => {
+                let mut executor = PurtelExecutor::new(self.tasks, self.param_usages);
+                executor.set_explicit_dependencies(self.dependencies);
+                executor.calc_and_verify_exe_order();
+                executor
+            },
+        }
+    }
+}
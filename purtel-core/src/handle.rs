@@ -0,0 +1,190 @@
+//! Shared handles. The data types that can be a parameter of a purtel task, i.e. that
+//! can be cloned into multiple tasks and synchronize the access of the tasks.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::access::PurtelShared;
+use crate::reduce::PurtelReduction;
+use crate::versioned::{PurtelVersioned, PurtelVersionMut};
+use crate::cell::{PurtelCell, PurtelCellRef, PurtelCellRefMut};
+use crate::slice::{PurtelSlice, PurtelSliceRef, PurtelSliceRefMut};
+
+/// Implemented by all handles to shared data that purtel supports as task parameter.
+/// `#[purtel_tasks]` uses this to type-check the declared parameters of each task
+/// and to acquire the guards of `#[purtel_task(..., guards)]`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a purtel shared handle",
+    label = "declared as task parameter here",
+    note = "use `Arc<RwLock<T>>`, `Arc<PurtelShared<T>>`, `Arc<PurtelCell<T>>`, `Arc<PurtelReduction<T>>`, `Arc<PurtelVersioned<T>>` or `Arc<PurtelSlice<T>>`"
+)]
+pub trait PurtelSharedHandle: Clone {
+    type Target: ?Sized;
+    type ReadGuard<'a>: Deref<Target = Self::Target> where Self: 'a;
+    type WriteGuard<'a>: DerefMut<Target = Self::Target> where Self: 'a;
+
+    /// Acquires shared access. Panics if the lock is poisoned.
+    fn read_guard(&self) -> Self::ReadGuard<'_>;
+
+    /// Acquires exclusive access. Panics if the lock is poisoned.
+    fn write_guard(&self) -> Self::WriteGuard<'_>;
+
+    /// Address of the shared data. Two handles with the same address refer to the same
+    /// data, see `PurtelAliases::register()`.
+    fn data_ptr(&self) -> *const ();
+
+    /// The identifier that the data reports its accesses with; `None` if it isn't
+    /// instrumented (`RwLock`).
+    fn identifier(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl<T> PurtelSharedHandle for Arc<RwLock<T>> {
+    type Target = T;
+    type ReadGuard<'a> = RwLockReadGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = RwLockWriteGuard<'a, T> where T: 'a;
+
+    fn read_guard(&self) -> Self::ReadGuard<'_> {
+        self.read().expect("RwLock must not be poisoned")
+    }
+
+    fn write_guard(&self) -> Self::WriteGuard<'_> {
+        self.write().expect("RwLock must not be poisoned")
+    }
+
+    fn data_ptr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+}
+
+impl<T> PurtelSharedHandle for Arc<PurtelShared<T>> {
+    type Target = T;
+    type ReadGuard<'a> = RwLockReadGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = RwLockWriteGuard<'a, T> where T: 'a;
+
+    fn read_guard(&self) -> Self::ReadGuard<'_> {
+        self.read()
+    }
+
+    fn write_guard(&self) -> Self::WriteGuard<'_> {
+        self.write()
+    }
+
+    fn data_ptr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(PurtelShared::identifier(self))
+    }
+}
+
+impl<T> PurtelSharedHandle for Arc<PurtelCell<T>> {
+    type Target = T;
+    type ReadGuard<'a> = PurtelCellRef<'a, T> where T: 'a;
+    type WriteGuard<'a> = PurtelCellRefMut<'a, T> where T: 'a;
+
+    fn read_guard(&self) -> Self::ReadGuard<'_> {
+        self.read()
+    }
+
+    fn write_guard(&self) -> Self::WriteGuard<'_> {
+        self.write()
+    }
+
+    fn data_ptr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(PurtelCell::identifier(self))
+    }
+}
+
+impl<T> PurtelSharedHandle for Arc<PurtelReduction<T>> {
+    type Target = T;
+    type ReadGuard<'a> = RwLockReadGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = RwLockWriteGuard<'a, T> where T: 'a;
+
+    fn read_guard(&self) -> Self::ReadGuard<'_> {
+        self.read()
+    }
+
+    fn write_guard(&self) -> Self::WriteGuard<'_> {
+        self.write()
+    }
+
+    fn data_ptr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(PurtelReduction::identifier(self))
+    }
+}
+
+impl<T: Clone> PurtelSharedHandle for Arc<PurtelVersioned<T>> {
+    type Target = T;
+    type ReadGuard<'a> = Arc<T> where T: 'a;
+    type WriteGuard<'a> = PurtelVersionMut<'a, T> where T: 'a;
+
+    fn read_guard(&self) -> Self::ReadGuard<'_> {
+        self.read()
+    }
+
+    fn write_guard(&self) -> Self::WriteGuard<'_> {
+        self.write()
+    }
+
+    fn data_ptr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(PurtelVersioned::identifier(self))
+    }
+}
+
+impl<T> PurtelSharedHandle for Arc<PurtelSlice<T>> {
+    type Target = [T];
+    type ReadGuard<'a> = PurtelSliceRef<'a, T> where T: 'a;
+    type WriteGuard<'a> = PurtelSliceRefMut<'a, T> where T: 'a;
+
+    fn read_guard(&self) -> Self::ReadGuard<'_> {
+        self.read(0..self.len())
+    }
+
+    fn write_guard(&self) -> Self::WriteGuard<'_> {
+        self.write(0..self.len())
+    }
+
+    fn data_ptr(&self) -> *const () {
+        Arc::as_ptr(self) as *const ()
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(PurtelSlice::identifier(self))
+    }
+}
+
+/// Fails to compile if `handle` isn't a purtel-supported shared handle. Used by the code
+/// that `#[purtel_tasks]` generates for each declared parameter; a misspelled parameter
+/// becomes an unknown variable.
+#[doc(hidden)]
+pub fn purtel_check_shared_handle<H: PurtelSharedHandle>(_handle: &H) {}
+
+/// Checks in debug builds that the data of a declared field parameter like `state.positions`
+/// reports its accesses with the same identifier, e.g. that `state` was created with
+/// `into_shared("state")`. Otherwise the dependencies of the tasks wouldn't refer to the
+/// data they access. Used by the code that `#[purtel_tasks]` generates.
+#[doc(hidden)]
+pub fn purtel_check_declared_identifier<H: PurtelSharedHandle>(handle: &H, declared: &str) {
+    if let Some(identifier) = handle.identifier() {
+        debug_assert!(
+            identifier == declared,
+            "The declared parameter '{}' is the data '{}'! The identifier of the data must be the \
+             name of the variable, e.g. `into_shared(\"state\")` for `state`.",
+            declared, identifier,
+        );
+    }
+}
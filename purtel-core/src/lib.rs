@@ -1,12 +1,50 @@
 //! Structs, data types, and functions for purtel: Phips userland runtime task execution library.
 
 mod types;
+mod context;
+mod cell;
+mod access;
+mod error;
+mod report;
+mod mode;
+mod fuzz;
+mod diff;
+mod schedule;
+mod handle;
+mod graph;
+mod alias;
+mod reduce;
+mod exclusive;
+mod versioned;
+mod slice;
+
+pub use crate::types::{TaskId, TaskDependencies, TaskExecutionLevel};
+pub use crate::cell::{PurtelCell, PurtelCellRef, PurtelCellRefMut};
+pub use crate::access::{PurtelAccess, PurtelAccessCheckMode, PurtelAccessViolation, PurtelShared};
+pub use crate::error::PurtelError;
+pub use crate::report::PurtelExecutionReport;
+pub use crate::mode::PurtelExecutionMode;
+pub use crate::fuzz::PurtelFuzzConfig;
+pub use crate::schedule::PurtelStaticSchedule;
+pub use crate::graph::PurtelTaskGraph;
+pub use crate::alias::PurtelAliases;
+pub use crate::reduce::PurtelReduction;
+pub use crate::versioned::{PurtelVersioned, PurtelVersionMut};
+pub use crate::slice::{PurtelSlice, PurtelSliceRef, PurtelSliceRefMut};
+pub use crate::handle::{PurtelSharedHandle, purtel_check_declared_identifier, purtel_check_shared_handle};
+pub use crate::diff::{PurtelDiffState, PurtelDiffParam, purtel_differential_check, purtel_differential_check_with_mode};
 
 use crate::PurtelTaskState::{WAITING, DISPATCHED};
 use std::thread;
-use std::sync::mpsc::channel;
-use crate::PurtelParamUsageKind::{READ, WRITE};
-use crate::types::{TaskId, TaskDependencies, TaskExecutionLevel};
+use std::sync::Arc;
+use std::ops::Range;
+use std::sync::mpsc::{channel, Receiver};
+use std::collections::HashMap;
+use crate::PurtelParamUsageKind::{EXCLUSIVE, READ, REDUCE, WRITE};
+use crate::access::declared_kind;
+use crate::exclusive::PurtelExclusionLocks;
+use crate::context::{PurtelTaskContext, PurtelTaskRecord, next_execution_id, run_in_context};
+use crate::fuzz::{FinishNotifier, FuzzRng, random_topological_order};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum PurtelTaskState {
@@ -18,20 +56,20 @@ pub struct PurtelTask {
     // This is an option because this memory
     // is taken from the purtel task to prepare
     // execution
-    closure: Option<Box<dyn FnOnce() -> () + Send>>,
+    closure: Option<Box<dyn FnOnce() + Send>>,
     state: PurtelTaskState,
 }
 
 impl PurtelTask {
 
-    pub fn new(closure: Box<dyn FnOnce() -> () + Send>) -> Self {
+    pub fn new(closure: Box<dyn FnOnce() + Send>) -> Self {
         Self {
             closure: Some(closure),
             state: WAITING,
         }
     }
 
-    pub fn take_task(&mut self) -> Box<dyn FnOnce() -> () + Send> {
+    pub fn take_task(&mut self) -> Box<dyn FnOnce() + Send> {
         if self.state != WAITING { panic!("Task is not in WAITING state!") }
         self.state = DISPATCHED;
         self.closure.take().expect("Must have value")
@@ -42,11 +80,27 @@ impl PurtelTask {
 pub enum PurtelParamUsageKind {
     READ,
     WRITE,
+    /// Commutative update, e.g. adding to a counter. Tasks that reduce the same parameter
+    /// don't depend on each other; see `PurtelReduction`.
+    REDUCE,
+    /// Exclusive access in any order (like OpenMP's `mutexinoutset`). Tasks that access the
+    /// same parameter exclusively don't depend on each other but never run at the same time.
+    /// Allows the same accesses as `WRITE`.
+    EXCLUSIVE,
 }
 
-#[derive(Debug)]
+impl PurtelParamUsageKind {
+    /// The kind that allows both kinds. READ and REDUCE together need exclusive access.
+    pub fn join(self, other: PurtelParamUsageKind) -> PurtelParamUsageKind {
+        if self == other { self } else { WRITE }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PurtelParamUsage {
     identifier: String,
+    /// only a half-open range of indices of the parameter is used; `None` means all of it
+    range: Option<Range<usize>>,
     kind: PurtelParamUsageKind,
 }
 
@@ -54,6 +108,17 @@ impl PurtelParamUsage {
     pub fn new(identifier: &str, kind: PurtelParamUsageKind) -> Self {
         Self {
             identifier: identifier.to_owned(),
+            range: None,
+            kind
+        }
+    }
+
+    /// Constructor for a usage of only the indices `range` of a parameter, e.g. a chunk
+    /// of a vector (`data1[0..1024]`). A single index `i` is the range `i..i + 1`.
+    pub fn with_range(identifier: &str, range: Range<usize>, kind: PurtelParamUsageKind) -> Self {
+        Self {
+            identifier: identifier.to_owned(),
+            range: Some(range),
             kind
         }
     }
@@ -62,17 +127,58 @@ impl PurtelParamUsage {
         &self.identifier
     }
 
+    pub fn range(&self) -> Option<&Range<usize>> {
+        self.range.as_ref()
+    }
+
     pub fn kind(&self) -> PurtelParamUsageKind {
         self.kind
     }
+
+    /// Identifiers are hierarchical: `world.entities` (or `world::entities`) is a child of
+    /// `world`. True if both usages refer to overlapping data, i.e. the identifiers are
+    /// equal or one is an ancestor of the other. Siblings like `world.entities` and
+    /// `world.lights` don't overlap. Two ranges of the same parameter only overlap if
+    /// they have an index in common.
+    pub fn overlaps(&self, other: &PurtelParamUsage) -> bool {
+        if let (Some(range), Some(other_range)) = (&self.range, &other.range) {
+            if self.identifier == other.identifier {
+                return range.start < other_range.end && other_range.start < range.end;
+            }
+        }
+        self.covers(&other.identifier) || other.covers(&self.identifier)
+    }
+
+    /// True if `identifier` is the identifier of this usage or a descendant of it.
+    pub fn covers(&self, identifier: &str) -> bool {
+        match identifier.strip_prefix(self.identifier.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with("::"),
+            None => false,
+        }
+    }
 }
 
 /// Struct that contains all tasks shat shall be executed by Purtel. It needs meta-data
 /// about the relation of the dependencies.
 pub struct PurtelExecutor {
-    param_usage_desc: Option<Vec<Vec<PurtelParamUsage>>>,
+    param_usage_desc: Vec<Vec<PurtelParamUsage>>,
+    task_deps: Option<Vec<TaskDependencies>>,
     exe_order: Option<Vec<TaskExecutionLevel>>,
     tasks: Vec<PurtelTask>,
+    access_check_mode: PurtelAccessCheckMode,
+    execution_mode: PurtelExecutionMode,
+    /// identifiers that refer to the same data, see `set_aliases()`
+    aliases: Arc<PurtelAliases>,
+    /// mutexes of the tasks with `EXCLUSIVE` usages; created by `execute()`
+    exclusion_locks: PurtelExclusionLocks,
+    /// parameters without Write-after-Read and Write-after-Write dependencies, see `rename()`
+    renamed: Vec<String>,
+    /// distinguishes the private copies of `PurtelReduction` and the versions of
+    /// `PurtelVersioned` of different executions
+    execution_id: u64,
+    /// dependencies `(task, after)` that aren't derived from the parameter usage,
+    /// see `add_dependency()`
+    explicit_deps: Vec<(TaskId, TaskId)>,
 }
 
 impl PurtelExecutor {
@@ -90,24 +196,141 @@ impl PurtelExecutor {
         dbg!(&param_usage_desc);
 
         Self {
+            task_deps: None,
             exe_order: None,
-            param_usage_desc: Some(param_usage_desc),
+            param_usage_desc,
             tasks,
+            access_check_mode: PurtelAccessCheckMode::OFF,
+            execution_mode: PurtelExecutionMode::PARALLEL,
+            aliases: Arc::new(PurtelAliases::new()),
+            exclusion_locks: PurtelExclusionLocks::default(),
+            renamed: vec![],
+            execution_id: next_execution_id(),
+            explicit_deps: vec![],
         }
     }
 
+    /// Constructor for tasks whose schedule was calculated ahead of time, usually at
+    /// compile time by `#[purtel_tasks]`. No runtime planning is needed, i.e. you must
+    /// *not* call `calc_and_verify_exe_order()` before `execute()`.
+    pub fn with_schedule(tasks: Vec<PurtelTask>,
+                         param_usage_desc: Vec<Vec<PurtelParamUsage>>,
+                         schedule: &PurtelStaticSchedule) -> Self {
+        assert_eq!(tasks.len(), schedule.task_deps().len(), "The schedule must cover every task!");
+        let mut executor = PurtelExecutor::new(tasks, param_usage_desc);
+        executor.task_deps = Some(schedule.task_deps().iter().map(|deps| deps.to_vec()).collect());
+        executor.exe_order = Some(schedule.exe_order().iter().map(|level| level.to_vec()).collect());
+        executor
+    }
+
+    /// Sets how `execute()` runs the tasks. Default is `PARALLEL`.
+    pub fn set_execution_mode(&mut self, mode: PurtelExecutionMode) {
+        self.execution_mode = mode;
+    }
+
+    /// Sets if and how accesses reported by instrumented shared data (`PurtelShared`)
+    /// are checked against the declared parameter usage. Default is `OFF`.
+    pub fn set_access_check_mode(&mut self, mode: PurtelAccessCheckMode) {
+        self.access_check_mode = mode;
+    }
+
+    /// Sets the alias groups of the parameter identifiers. Aliases are the same data for the
+    /// dependency analysis and the access checks. Replaces a precomputed schedule because
+    /// aliases can add dependencies that the schedule doesn't know about.
+    pub fn set_aliases(&mut self, aliases: PurtelAliases) {
+        self.aliases = Arc::new(aliases);
+        if self.exe_order.is_some() {
+            self.exe_order = None;
+            self.task_deps = None;
+            self.calc_and_verify_exe_order();
+        }
+    }
+
+    /// Enables renaming for the parameter of the given data (and its children): a writer
+    /// creates a new version while the tasks before it keep reading the old one. Hence a
+    /// writer doesn't wait for the readers and writers before it; there are no Write-after-Read
+    /// and no Write-after-Write dependencies, only Read after Write. Replaces a precomputed
+    /// schedule.
+    ///
+    /// The writers must create their version with `PurtelVersioned::replace()`, which doesn't
+    /// read the previous version. `PurtelVersioned::write()` starts from the previous version
+    /// and panics on renamed data, because the writer before may not have finished.
+    ///
+    /// The versions are ordered by task ID. Fails with `PurtelError::RenamingConflict` if an
+    /// explicit dependency (`add_dependency()`) lets a task that uses the data run after
+    /// a task with a higher ID that uses it as well; renaming isn't enabled in this case.
+    pub fn rename<T: Clone>(&mut self, data: &PurtelVersioned<T>) -> Result<(), PurtelError> {
+        self.renamed.push(data.identifier().to_owned());
+        if let Err(err) = self.calc_dependencies() {
+            self.renamed.pop();
+            return Err(err);
+        }
+        if self.exe_order.is_some() {
+            self.exe_order = None;
+            self.task_deps = None;
+            self.calc_and_verify_exe_order();
+        }
+        Ok(())
+    }
+
+    /// Adds a dependency of task `task_id` on task `after` that doesn't follow from the
+    /// parameter usage, e.g. for the order of log output or other side effects. `after` may
+    /// also have a higher ID. Fails with `PurtelError::DependencyCycle` if the dependencies
+    /// would contain a cycle and with `PurtelError::RenamingConflict` if `after` has a higher
+    /// ID and both use a renamed parameter (see `rename()`); the dependency isn't added in
+    /// these cases. Replaces a precomputed schedule.
+    pub fn add_dependency(&mut self, task_id: TaskId, after: TaskId) -> Result<(), PurtelError> {
+        assert!(task_id < self.tasks.len() && after < self.tasks.len(), "Unknown task id!");
+        self.explicit_deps.push((task_id, after));
+        if let Err(err) = self.calc_dependencies() {
+            self.explicit_deps.pop();
+            return Err(err);
+        }
+        if self.exe_order.is_some() {
+            self.exe_order = None;
+            self.task_deps = None;
+            self.calc_and_verify_exe_order();
+        }
+        Ok(())
+    }
+
+    /// Sets the explicit dependencies without a check and without replacing a precomputed
+    /// schedule; `#[purtel_tasks]` already added them to the schedule it calculated.
+    pub(crate) fn set_explicit_dependencies(&mut self, dependencies: Vec<(TaskId, TaskId)>) {
+        self.explicit_deps = dependencies;
+    }
+
+    /// Returns the declared param usage of a task with canonical identifiers (see `set_aliases()`).
+    fn canonical_usages(&self, task_id: TaskId) -> Vec<PurtelParamUsage> {
+        self.aliases.canonical_usages(&self.param_usage_desc[task_id])
+    }
+
+    /// Returns all accesses of `accesses` that exceed the declared parameter usage.
+    fn find_access_violations(&self, accesses: &[PurtelAccess]) -> Vec<PurtelAccessViolation> {
+        accesses.iter()
+            .filter_map(|access| {
+                let declared = declared_kind(
+                    &self.canonical_usages(access.task_id()),
+                    &self.aliases.canonical(access.identifier()),
+                );
+                PurtelAccessViolation::check(access, declared)
+            })
+            .collect()
+    }
+
     /// Helper function for `calc_task_dependencies()`. Asserts there are no duplicates
     /// (to prevent human error for example). This means every task can list a parameter only
     /// once. This also ensures that if a parameter is declared as write it is not also
     /// declared as read.
-    fn assert_no_duplicates(param_usages: &Vec<Vec<PurtelParamUsage>>) {
+    pub fn assert_no_duplicates(param_usages: &[Vec<PurtelParamUsage>]) {
         for (task_i, param_usage) in param_usages.iter().enumerate() {
             // We check that each parameter ID is contained only once
             for (p_i, p_desc) in param_usage.iter().enumerate() {
                 let p_id = &p_desc.identifier;
-                for p_j in (p_i + 1)..param_usage.len() {
-                    let p_j_id = &param_usage[p_j].identifier;
-                    if p_id == p_j_id {
+                for p_j_desc in param_usage.iter().skip(p_i + 1) {
+                    let p_j_id = &p_j_desc.identifier;
+                    // disjoint ranges of the same parameter are fine
+                    if p_id == p_j_id && p_desc.overlaps(p_j_desc) {
                         panic!("Task {} declares usage for parameter '{}' multiple times, that's illegal!", task_i, p_id);
                     }
                 }
@@ -118,9 +341,15 @@ impl PurtelExecutor {
     /// Calculates the dependencies for each task id/index on other task ids/indices. This is done
     /// by an analysis of the parameter usage per task index. A dependency to a previous task exists
     /// iff:
-    ///   - a task with a lower id has write access to the same parameter (Read after Write), or
-    ///   - a task with a lower id has read access to a parameter that this tasks
-    ///     needs right access for (Write after Read, Write After Write)
+    /// - a task with a lower id has write access to the same parameter (Read after Write), or
+    /// - a task with a lower id has read access to a parameter that this tasks
+    ///   needs right access for (Write after Read, Write After Write), or
+    /// - one of both reduces the parameter and the other one doesn't (Reduce after Read,
+    ///   Read after Reduce, ...). Reduce after Reduce commutes and needs no dependency, or
+    /// - one of both accesses the parameter exclusively and the other one doesn't. Exclusive
+    ///   after Exclusive needs no dependency; the executor only prevents that both run at
+    ///   the same time.
+    ///
     /// The resulting vector is a vector per task (index) that contains all task indices that must
     /// be finished before the task can run.
     ///
@@ -129,9 +358,22 @@ impl PurtelExecutor {
     /// The overhead is (probably even for thousands of tasks?) negligible.
     ///
     /// Tasks with the same count of dependencies can never be dependent on each other.
-    fn calc_task_dependencies(param_usages: &Vec<Vec<PurtelParamUsage>>) -> Vec<TaskDependencies> {
+    ///
+    /// Public because purtel-codegen calculates the schedule at compile time.
+    pub fn calc_task_dependencies(param_usages: &[Vec<PurtelParamUsage>]) -> Vec<TaskDependencies> {
+        PurtelExecutor::calc_task_dependencies_with_renaming(param_usages, &[])
+    }
+
+    /// Like `calc_task_dependencies()` but without Write-after-Read and Write-after-Write
+    /// dependencies between usages of the parameters `renamed` (and their children), see
+    /// `rename()`: each writer creates a new version.
+    pub fn calc_task_dependencies_with_renaming(param_usages: &[Vec<PurtelParamUsage>],
+                                                renamed: &[String]) -> Vec<TaskDependencies> {
+        let is_renamed = |usage: &PurtelParamUsage| renamed.iter()
+            .any(|identifier| PurtelParamUsage::new(identifier, WRITE).covers(usage.identifier()));
+
         // checks if parameter usage is properly defined
-        PurtelExecutor::assert_no_duplicates(&param_usages);
+        PurtelExecutor::assert_no_duplicates(param_usages);
 
         let mut all_dependencies = vec![];
         // for each tasks
@@ -142,27 +384,32 @@ impl PurtelExecutor {
             let mut task_dependencies = vec![];
 
             // for each param per task
-            for task_i_param_i in 0..param_usages[task_i].len() {
+            for param in &param_usages[task_i] {
                 // we check if a dependence to a prevous task exists
-                let param = &param_usages[task_i][task_i_param_i];
 
                 // check all params that previous tasks use
-                for prev_task_i in 0..task_i {
+                for (prev_task_i, prev_task_params) in param_usages.iter().enumerate().take(task_i) {
                     // for each param of previous tasks
-                    for prev_task_i_param_i in 0..param_usages[prev_task_i].len() {
-                        let prev_param = &param_usages[prev_task_i][prev_task_i_param_i];
-                        // true if: a previous tasks uses the same parameter
+                    for prev_param in prev_task_params {
+                        // true if: a previous tasks uses the same parameter,
+                        // a parent or a child of it
                         let already_in_deps = task_dependencies.contains(&prev_task_i);
-                        if param.identifier == prev_param.identifier && !already_in_deps {
+                        if param.overlaps(prev_param) && !already_in_deps {
                             // Dependency exists iff:
                             // - prev usage is write
                             // - current usage is write and prev usage is read
-                            let write_after_write = param.kind() == WRITE && prev_param.kind() == WRITE;
-                            let write_after_read = param.kind() == WRITE && prev_param.kind() == READ;
+                            // the writer creates a new version if the parameter is renamed
+                            let both_renamed = is_renamed(param) && is_renamed(prev_param);
+                            let write_after_write = param.kind() == WRITE && prev_param.kind() == WRITE && !both_renamed;
+                            let write_after_read = param.kind() == WRITE && prev_param.kind() == READ && !both_renamed;
                             // let read_after_read = param.kind() == READ && prev_param.kind() == READ;
                             let read_after_write = param.kind() == READ && prev_param.kind() == WRITE;
+                            // let reduce_after_reduce = param.kind() == REDUCE && prev_param.kind() == REDUCE;
+                            let mixed_reduce = (param.kind() == REDUCE) != (prev_param.kind() == REDUCE);
+                            // let exclusive_after_exclusive = param.kind() == EXCLUSIVE && prev_param.kind() == EXCLUSIVE;
+                            let mixed_exclusive = (param.kind() == EXCLUSIVE) != (prev_param.kind() == EXCLUSIVE);
 
-                            if write_after_read || write_after_write || read_after_write {
+                            if write_after_read || write_after_write || read_after_write || mixed_reduce || mixed_exclusive {
                                 // task "task_i" has dependency to task "prev_task_i"
                                 task_dependencies.push(prev_task_i);
                             }
@@ -175,6 +422,54 @@ impl PurtelExecutor {
         all_dependencies
     }
 
+    /// Adds the explicit dependencies `(task, after)` (see `add_dependency()`) to the
+    /// dependencies calculated by `calc_task_dependencies()`. Fails with
+    /// `PurtelError::DependencyCycle` if the result contains a cycle.
+    ///
+    /// Public because purtel-codegen calculates the schedule at compile time.
+    pub fn add_explicit_dependencies(task_deps: &mut [TaskDependencies],
+                                     dependencies: &[(TaskId, TaskId)]) -> Result<(), PurtelError> {
+        for (task_id, after) in dependencies {
+            if !task_deps[*task_id].contains(after) {
+                task_deps[*task_id].push(*after);
+            }
+        }
+        match PurtelExecutor::find_dependency_cycle(task_deps) {
+            Some(cycle) => Err(PurtelError::DependencyCycle(cycle)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the tasks of a cycle in the dependencies, if there is one: each task depends
+    /// on the next one and the last one on the first one. Derived dependencies alone can't
+    /// form a cycle because they only point to tasks with a lower ID.
+    fn find_dependency_cycle(task_deps: &[TaskDependencies]) -> Option<Vec<TaskId>> {
+        // depth-first search; `path` holds the tasks whose dependencies are being visited
+        fn visit(task_id: TaskId,
+                 task_deps: &[TaskDependencies],
+                 visited: &mut [bool],
+                 path: &mut Vec<TaskId>) -> Option<Vec<TaskId>> {
+            if let Some(start) = path.iter().position(|id| *id == task_id) {
+                return Some(path[start..].to_vec());
+            }
+            if visited[task_id] {
+                return None;
+            }
+            visited[task_id] = true;
+            path.push(task_id);
+            for dep_task_id in &task_deps[task_id] {
+                if let Some(cycle) = visit(*dep_task_id, task_deps, visited, path) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            None
+        }
+
+        let mut visited = vec![false; task_deps.len()];
+        (0..task_deps.len()).find_map(|task_id| visit(task_id, task_deps, &mut visited, &mut vec![]))
+    }
+
     /// Execution order is a Vector of Vector of task indices. The
     /// primary vector describes in how many iterations several tasks
     /// are bundles and executed in parallel ("execution level").
@@ -185,9 +480,11 @@ impl PurtelExecutor {
     /// execution level can never be dependent on each other. But they can
     /// have concurrent read to the same data.
     ///  * `task_deps: Vec<Vec<usize>>`: Vector with all dependencies per
-    ///                                  task id. A dependency is a task id
-    ///                                  that can only be less than the current task_id
-    fn calc_execution_levels(task_deps: Vec<TaskDependencies>) -> Vec<TaskExecutionLevel> {
+    ///    task id. A dependency is a task id that is less than the current
+    ///    task_id, unless it was added explicitly; there must be no cycle
+    ///
+    /// Public because purtel-codegen calculates the schedule at compile time.
+    pub fn calc_execution_levels(task_deps: Vec<TaskDependencies>) -> Vec<TaskExecutionLevel> {
         let mut execution_levels: Vec<Vec<usize>> = vec![];
 
         // Vector that maps from index (task id) to Option. The option describes whether
@@ -245,8 +542,8 @@ impl PurtelExecutor {
     /// Helper function for `calculate_exe_order` that checks if all tasks that the specified
     /// task is dependent from are already assigned to previous execution levels.
     fn all_deps_already_assigned(id: TaskId,
-                                 execution_levels: &Vec<TaskExecutionLevel>,
-                                 task_deps: &Vec<TaskDependencies>) -> bool {
+                                 execution_levels: &[TaskExecutionLevel],
+                                 task_deps: &[TaskDependencies]) -> bool {
         // ids of all tasks that this task is dependent from
         let task_deps = &task_deps[id];
 
@@ -263,8 +560,7 @@ impl PurtelExecutor {
             // at this point execution_levels only contains "complete" levels,
             // e.g. verified data; working set is not part of the vector yet; because of
             // this "-1" is not necessary at upper bound
-            for tasks_of_level_i in 0..execution_levels.len() {
-                let tasks_of_level = &execution_levels[tasks_of_level_i];
+            for tasks_of_level in execution_levels {
                 if tasks_of_level.contains(dep_task_id) {
                     found = true;
                     break;
@@ -297,56 +593,301 @@ impl PurtelExecutor {
 
     /// Calculates an optimized order in which the tasks shall be executed.
     pub fn calc_and_verify_exe_order(&mut self) {
-        // here we calculate which task id is dependent on what task ids
-        let deps = PurtelExecutor::calc_task_dependencies(
-            // take() to free memory; memory for footprint of thousands of tasks
-            // may be big otherwise; also we do not need this any more
-            &self.param_usage_desc.take().expect("calc_and_verify_exe_order() should only be called once!")
-        );
+        assert!(self.exe_order.is_none(), "calc_and_verify_exe_order() should only be called once and not for a precomputed schedule!");
+
+        // here we calculate which task id is dependent on what task ids;
+        // `add_dependency()` already rejected cycles, only new aliases can add one
+        let deps = self.calc_dependencies().unwrap_or_else(|err| panic!("{}", err));
 
         dbg!("found following dependencies");
         dbg!(&deps);
 
         // calculate an optimized execution order
-        let exe_order = PurtelExecutor::calc_execution_levels(deps);
+        let exe_order = PurtelExecutor::calc_execution_levels(deps.clone());
         self.exe_order = Some(exe_order);
+        // needed for the dynamic dispatch in mode `FUZZ`
+        self.task_deps = Some(deps);
 
         // this should only fail if my algorithm does weird things
         // check if not more levels than tasks exists
         debug_assert_eq!(0, self.exe_order.iter().filter(|vec| vec.is_empty()).count(), "Empty execution levels are invalid!");
     }
 
-    /// Executes the tasks in an optimal order in a parallelized way.
-    /// You *must* call `calc_and_verify_exe_order()` first.
-    pub fn execute(mut self) {
+    /// Helper function for `calc_and_verify_exe_order()` and `add_dependency()`: the
+    /// dependencies derived from the parameter usage plus the explicit ones.
+    fn calc_dependencies(&self) -> Result<Vec<TaskDependencies>, PurtelError> {
+        // param usages are kept because tasks get validated against them during execution;
+        // aliases of the same data must be found as conflicts
+        let param_usages = (0..self.tasks.len())
+            .map(|task_id| self.canonical_usages(task_id))
+            .collect::<Vec<_>>();
+        let renamed = self.canonical_renamed();
+        let mut deps = PurtelExecutor::calc_task_dependencies_with_renaming(&param_usages, &renamed);
+        PurtelExecutor::add_explicit_dependencies(&mut deps, &self.explicit_deps)?;
+        PurtelExecutor::check_renaming_order(&deps, &param_usages, &renamed)?;
+        Ok(deps)
+    }
+
+    /// The renamed parameters (see `rename()`) with canonical identifiers.
+    fn canonical_renamed(&self) -> Vec<String> {
+        self.renamed.iter()
+            .map(|identifier| self.aliases.canonical(identifier))
+            .collect()
+    }
+
+    /// Helper for `calc_dependencies()`. A task sees the version of a renamed parameter of
+    /// the last writer with a lower ID. Fails if a task runs after a task with a higher ID
+    /// (only possible with explicit dependencies) and they use the same renamed data; two
+    /// readers see the same version anyway.
+    fn check_renaming_order(task_deps: &[TaskDependencies],
+                            param_usages: &[Vec<PurtelParamUsage>],
+                            renamed: &[String]) -> Result<(), PurtelError> {
+        let renamed_usages = |task_id: TaskId| param_usages[task_id].iter()
+            .filter(|usage| renamed.iter().any(|identifier| PurtelParamUsage::new(identifier, WRITE).covers(usage.identifier())))
+            .collect::<Vec<_>>();
+        for task_id in 0..task_deps.len() {
+            let usages = renamed_usages(task_id);
+            if usages.is_empty() {
+                continue;
+            }
+            // all tasks that run before the task
+            let mut before = vec![false; task_deps.len()];
+            let mut stack = task_deps[task_id].clone();
+            while let Some(dep) = stack.pop() {
+                if !before[dep] {
+                    before[dep] = true;
+                    stack.extend(&task_deps[dep]);
+                }
+            }
+            for later in (task_id + 1..task_deps.len()).filter(|later| before[*later]) {
+                let conflict = renamed_usages(later).into_iter()
+                    .find(|other| usages.iter()
+                        .any(|usage| usage.overlaps(other) && (usage.kind() != READ || other.kind() != READ)));
+                if let Some(other) = conflict {
+                    return Err(PurtelError::RenamingConflict(other.identifier().to_owned(), task_id, later));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes the tasks in an optimal order. By default in a parallelized way,
+    /// see `set_execution_mode()`. You *must* call `calc_and_verify_exe_order()` first.
+    ///
+    /// Fails if the access check mode is `ENFORCE` and a task accessed instrumented
+    /// shared data beyond its declaration. In this case no further execution levels run.
+    /// Same if the aliases are detected by registering the handles (see `PurtelAliases`)
+    /// and a task accessed instrumented shared data that wasn't registered.
+    pub fn execute(mut self) -> Result<PurtelExecutionReport, PurtelError> {
         assert!(self.exe_order.is_some(), "Call calc_and_verify_exe_order() first!");
+        let param_usages = (0..self.tasks.len())
+            .map(|task_id| self.canonical_usages(task_id))
+            .collect::<Vec<_>>();
+        self.exclusion_locks = PurtelExclusionLocks::new(&param_usages);
+        if let PurtelExecutionMode::FUZZ(config) = self.execution_mode {
+            return self.execute_fuzzed(config);
+        }
+
+        let exe_order = match self.execution_mode {
+            // every task is its own level; this way the report reflects the real order
+            PurtelExecutionMode::SEQUENTIAL_BY_ID => self.id_order()
+                .into_iter()
+                .map(|task_id| vec![task_id])
+                .collect(),
+            PurtelExecutionMode::PARALLEL | PurtelExecutionMode::SEQUENTIAL_BY_LEVEL => {
+                self.exe_order.take().unwrap()
+            }
+            PurtelExecutionMode::FUZZ(_) => unreachable!(),
+        };
+        let mut accesses = vec![];
 
         dbg!("execute all tasks in the following order");
-        dbg!(self.exe_order.as_ref().unwrap());
-
-        for task_ids in self.exe_order.unwrap() {
-            let mut handles = vec![];
-            for task_id in &task_ids {
-                // Channel needed to safely transfer heap data (Box<>) into a thread
-                let (sender, receiver) = channel();
-                let task = self.tasks[*task_id].take_task();
-                sender.send(task).expect("Transfer of closure into thread must work");
-                let h = thread::spawn(move || {
-                    eprintln!("thread spawned!");
-                    let closure = receiver.recv().expect("Must receive closure/task!");
-                    closure();
-                    eprintln!("thread stopped!");
-                });
-                handles.push(h);
+        dbg!(&exe_order);
+
+        for task_ids in &exe_order {
+            let level_record = match self.execution_mode {
+                PurtelExecutionMode::PARALLEL => self.execute_level_parallel(task_ids),
+                PurtelExecutionMode::SEQUENTIAL_BY_ID | PurtelExecutionMode::SEQUENTIAL_BY_LEVEL => {
+                    self.execute_level_sequential(task_ids)
+                }
+                PurtelExecutionMode::FUZZ(_) => unreachable!(),
+            };
+
+            if !level_record.unregistered.is_empty() {
+                return Err(PurtelError::UnregisteredHandle(level_record.unregistered));
+            }
+            if self.access_check_mode == PurtelAccessCheckMode::ENFORCE {
+                let violations = self.find_access_violations(&level_record.accesses);
+                if !violations.is_empty() {
+                    return Err(PurtelError::UndeclaredAccess(violations));
+                }
+            }
+            accesses.extend(level_record.accesses);
+        }
+
+        Ok(PurtelExecutionReport::new(self.execution_mode, exe_order, accesses))
+    }
+
+    /// The order of mode `SEQUENTIAL_BY_ID`: always the ready task with the lowest ID. That's
+    /// exactly the `TaskId` order unless a task has an explicit dependency on a task with
+    /// a higher ID.
+    fn id_order(&self) -> Vec<TaskId> {
+        let task_deps = self.task_deps.as_ref().expect("Call calc_and_verify_exe_order() first!");
+        let mut order = Vec::with_capacity(task_deps.len());
+        let mut placed = vec![false; task_deps.len()];
+        while order.len() < task_deps.len() {
+            let task_id = (0..task_deps.len())
+                .find(|task_id| !placed[*task_id] && task_deps[*task_id].iter().all(|dep| placed[*dep]))
+                .expect("No task is ready! Deadlock or algorithm error?");
+            placed[task_id] = true;
+            order.push(task_id);
+        }
+        order
+    }
+
+    /// Takes the closure of a task for execution. Tasks with `EXCLUSIVE` usages hold their
+    /// mutexes while they run.
+    fn take_task(&mut self, task_id: TaskId) -> Box<dyn FnOnce() + Send> {
+        let task = self.tasks[task_id].take_task();
+        self.exclusion_locks.wrap(task_id, task)
+    }
+
+    /// Creates the context for a task that will be installed on the thread that runs it.
+    fn task_context(&self, task_id: TaskId) -> PurtelTaskContext {
+        // declared param usage; shared data types validate accesses against it
+        PurtelTaskContext::new(
+            task_id,
+            self.execution_id,
+            self.canonical_usages(task_id),
+            self.aliases.clone(),
+            self.canonical_renamed(),
+            self.access_check_mode != PurtelAccessCheckMode::OFF,
+        )
+    }
+
+    /// Runs all tasks of an execution level in parallel; one thread per task.
+    /// Returns after all tasks finished. Returns the reported accesses.
+    fn execute_level_parallel(&mut self, task_ids: &[TaskId]) -> PurtelTaskRecord {
+        let mut handles = vec![];
+        for task_id in task_ids {
+            // Channel needed to safely transfer heap data (Box<>) into a thread
+            let (sender, receiver) = channel();
+            let task = self.take_task(*task_id);
+            sender.send(task).expect("Transfer of closure into thread must work");
+            let context = self.task_context(*task_id);
+            let h = thread::spawn(move || {
+                eprintln!("thread spawned!");
+                let closure = receiver.recv().expect("Must receive closure/task!");
+                let record = run_in_context(context, closure);
+                eprintln!("thread stopped!");
+                record
+            });
+            handles.push(h);
+        }
+
+        // synchronously wait for all threads of current iteration level to finish and succeed!
+        let mut level_record = PurtelTaskRecord::default();
+        handles.into_iter().for_each(|h| {
+            level_record.extend(h.join().expect("Thread must succeed!"));
+            // doesn't work, rust compiler complains move errors...
+            // self.tasks[task_i].finish();
+        });
+        level_record
+    }
+
+    /// Implementation of `PurtelExecutionMode::FUZZ`. The tasks start in a random
+    /// topological order. A task starts as soon as it is its turn and all tasks it
+    /// depends on are finished; hence independent tasks still run in parallel.
+    fn execute_fuzzed(mut self, config: PurtelFuzzConfig) -> Result<PurtelExecutionReport, PurtelError> {
+        let task_deps = self.task_deps.take().unwrap();
+        let mut rng = FuzzRng::new(config.seed());
+        let order = random_topological_order(&task_deps, &mut rng);
+
+        let (done_sender, done_receiver) = channel();
+        let mut handles = HashMap::new();
+        let mut finished = vec![false; self.tasks.len()];
+        let mut accesses = vec![];
+        let mut violations = vec![];
+        let mut unregistered = vec![];
+
+        for task_id in &order {
+            // wait until all dependencies are finished; stop early on a violation
+            while violations.is_empty() && unregistered.is_empty()
+                && !task_deps[*task_id].iter().all(|dep| finished[*dep]) {
+                let (done_id, record) = Self::join_next_finished(&done_receiver, &mut handles, config.seed());
+                finished[done_id] = true;
+                violations.extend(self.enforced_access_violations(&record.accesses));
+                accesses.extend(record.accesses);
+                unregistered.extend(record.unregistered);
+            }
+            if !violations.is_empty() || !unregistered.is_empty() {
+                break;
             }
 
-            // synchronously wait for all threads of current iteration level to finish and succeed!
-            handles.into_iter().for_each(|h| {
-                h.join().expect("Thread must succeed!");
-                // doesn't work, rust compiler complains move errors...
-                // self.tasks[task_i].finish();
+            let task = self.take_task(*task_id);
+            let context = self.task_context(*task_id);
+            let delay = config.max_delay().map(|max| rng.next_delay(max));
+            let notifier = FinishNotifier::new(*task_id, done_sender.clone());
+            let h = thread::spawn(move || {
+                let _notifier = notifier;
+                if let Some(delay) = delay {
+                    thread::sleep(delay);
+                }
+                run_in_context(context, task)
             });
+            handles.insert(*task_id, h);
+        }
+
+        // wait for all tasks that are still running
+        while !handles.is_empty() {
+            let (_, record) = Self::join_next_finished(&done_receiver, &mut handles, config.seed());
+            violations.extend(self.enforced_access_violations(&record.accesses));
+            accesses.extend(record.accesses);
+            unregistered.extend(record.unregistered);
+        }
+
+        if !unregistered.is_empty() {
+            return Err(PurtelError::UnregisteredHandle(unregistered));
         }
+        if !violations.is_empty() {
+            return Err(PurtelError::UndeclaredAccess(violations));
+        }
+
+        let exe_order = order.into_iter().map(|task_id| vec![task_id]).collect();
+        Ok(PurtelExecutionReport::new(self.execution_mode, exe_order, accesses))
+    }
+
+    /// Helper for `execute_fuzzed()`. Blocks until the next task thread finishes and
+    /// joins it. Returns the ID of the task and its reported accesses.
+    fn join_next_finished(done_receiver: &Receiver<TaskId>,
+                          handles: &mut HashMap<TaskId, thread::JoinHandle<PurtelTaskRecord>>,
+                          seed: u64) -> (TaskId, PurtelTaskRecord) {
+        let task_id = done_receiver.recv().expect("Must receive finished task!");
+        let h = handles.remove(&task_id).expect("Finished task must have a thread!");
+        let record = h.join()
+            .unwrap_or_else(|_| panic!("Task {} failed! Reproduce with fuzz seed {}.", task_id, seed));
+        (task_id, record)
+    }
+
+    /// Returns all violations in `accesses` if the access check mode is `ENFORCE`.
+    fn enforced_access_violations(&self, accesses: &[PurtelAccess]) -> Vec<PurtelAccessViolation> {
+        if self.access_check_mode == PurtelAccessCheckMode::ENFORCE {
+            self.find_access_violations(accesses)
+        } else {
+            vec![]
+        }
+    }
+
+    /// Runs the given tasks one after another on the calling thread.
+    /// Returns the reported accesses.
+    fn execute_level_sequential(&mut self, task_ids: &[TaskId]) -> PurtelTaskRecord {
+        let mut level_record = PurtelTaskRecord::default();
+        for task_id in task_ids {
+            let task = self.take_task(*task_id);
+            let context = self.task_context(*task_id);
+            level_record.extend(run_in_context(context, task));
+        }
+        level_record
     }
 
 }
@@ -355,7 +896,8 @@ impl PurtelExecutor {
 mod tests {
 
     use super::*;
-    use crate::PurtelParamUsageKind::{READ, WRITE};
+    use crate::PurtelParamUsageKind::{EXCLUSIVE, READ, REDUCE, WRITE};
+    use std::sync::Arc;
 
     #[test]
     pub fn test_calc_dependencies_simple() {
@@ -470,6 +1012,811 @@ mod tests {
         PurtelExecutor::assert_no_duplicates(&param_usages);
     }
 
+    #[test]
+    pub fn test_purtel_cell_declared_access() {
+        let data1 = Arc::new(PurtelCell::new("data1", vec![1, 2, 3]));
+        let data1_t1 = data1.clone();
+        let data1_t2 = data1.clone();
+        let data1_t3 = data1.clone();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || data1_t1.write().push(4))),
+            PurtelTask::new(Box::new(move || assert_eq!(4, data1_t2.read().len()))),
+            PurtelTask::new(Box::new(move || assert_eq!(4, data1_t3.read().len()))),
+        ];
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data1", READ)],
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.calc_and_verify_exe_order();
+        executor.execute().unwrap();
+
+        let data1 = Arc::try_unwrap(data1).ok().expect("all tasks are finished");
+        assert_eq!(vec![1, 2, 3, 4], data1.into_inner());
+    }
+
+    #[test]
+    pub fn test_access_check_record() {
+        let data1 = Arc::new(PurtelShared::new("data1", 1));
+        let data1_t1 = data1.clone();
+        let data1_t2 = data1.clone();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || *data1_t1.write() += 1)),
+            PurtelTask::new(Box::new(move || assert_eq!(2, *data1_t2.read()))),
+        ];
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.set_access_check_mode(PurtelAccessCheckMode::RECORD);
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+
+        assert_eq!(2, report.exe_order().len());
+        assert_eq!(
+            &[PurtelAccess::new(0, "data1", WRITE), PurtelAccess::new(1, "data1", READ)],
+            report.accesses()
+        );
+    }
+
+    #[test]
+    pub fn test_access_check_enforce_undeclared() {
+        let data1 = Arc::new(PurtelShared::new("data1", 1));
+        let data2 = Arc::new(PurtelShared::new("data2", 1));
+        let data1_t1 = data1.clone();
+        let data2_t1 = data2.clone();
+        let data2_t2 = data2.clone();
+        let tasks = vec![
+            // writes data1 but only declares read; reads data2 without declaring it
+            PurtelTask::new(Box::new(move || *data1_t1.write() += *data2_t1.read())),
+            PurtelTask::new(Box::new(move || *data2_t2.write() += 1)),
+        ];
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.set_access_check_mode(PurtelAccessCheckMode::ENFORCE);
+        executor.calc_and_verify_exe_order();
+
+        let err = executor.execute().unwrap_err();
+        let violations = match &err {
+            PurtelError::UndeclaredAccess(violations) => violations,
+            other => panic!("unexpected error: {}", other),
+        };
+        assert_eq!(2, violations.len(), "both accesses of task 0 exceed the declaration");
+        assert!(violations.iter().all(|v| v.access().task_id() == 0));
+        let undeclared = violations.iter().find(|v| v.access().identifier() == "data2").unwrap();
+        assert_eq!(None, undeclared.declared());
+        let exceeded = violations.iter().find(|v| v.access().identifier() == "data1").unwrap();
+        assert_eq!(Some(READ), exceeded.declared());
+        assert!(err.to_string().contains("task 0 accessed parameter 'data2' as READ but didn't declare it"));
+        // task 1 is in the same level and ran, but nothing afterwards would
+        assert_eq!(2, *data2.read());
+    }
+
+    /// Creates tasks that log their ID and the thread they ran on.
+    fn logging_tasks(n: usize, log: &Arc<std::sync::Mutex<Vec<(TaskId, thread::ThreadId)>>>) -> Vec<PurtelTask> {
+        (0..n)
+            .map(|task_id| {
+                let log = log.clone();
+                PurtelTask::new(Box::new(move || {
+                    log.lock().unwrap().push((task_id, thread::current().id()));
+                }))
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_execute_sequential_by_id() {
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", READ)],
+        ];
+        let mut executor = PurtelExecutor::new(logging_tasks(4, &log), param_usages);
+        executor.set_execution_mode(PurtelExecutionMode::SEQUENTIAL_BY_ID);
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+
+        assert_eq!(PurtelExecutionMode::SEQUENTIAL_BY_ID, report.mode());
+        assert_eq!(&[vec![0], vec![1], vec![2], vec![3]], report.exe_order());
+        let log = log.lock().unwrap();
+        assert_eq!(vec![0, 1, 2, 3], log.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert!(log.iter().all(|(_, t)| *t == thread::current().id()), "no thread must be spawned");
+    }
+
+    #[test]
+    pub fn test_execute_sequential_by_level() {
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+        ];
+        let mut executor = PurtelExecutor::new(logging_tasks(3, &log), param_usages);
+        executor.set_execution_mode(PurtelExecutionMode::SEQUENTIAL_BY_LEVEL);
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+
+        assert_eq!(&[vec![0, 2], vec![1]], report.exe_order());
+        let log = log.lock().unwrap();
+        assert_eq!(vec![0, 2, 1], log.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert!(log.iter().all(|(_, t)| *t == thread::current().id()), "no thread must be spawned");
+    }
+
+    #[test]
+    pub fn test_hierarchical_identifiers() {
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("world.entities", WRITE)],
+            vec![PurtelParamUsage::new("world.lights", WRITE)],
+            // parent: conflicts with both children
+            vec![PurtelParamUsage::new("world", READ)],
+            // child of a child
+            vec![PurtelParamUsage::new("world.entities.0", WRITE)],
+            // only a common prefix, no child
+            vec![PurtelParamUsage::new("worldmap", WRITE)],
+            vec![PurtelParamUsage::new("world.lights.sun", READ)],
+        ];
+        let deps = PurtelExecutor::calc_task_dependencies(&param_usages);
+        assert_eq!(vec![vec![], vec![], vec![0, 1], vec![0, 2], vec![], vec![1]], deps);
+
+        // access to a child of a declared parameter is declared
+        let world = Arc::new(PurtelShared::new("world.entities", 1));
+        let world_t = world.clone();
+        let tasks = vec![PurtelTask::new(Box::new(move || *world_t.write() += 1))];
+        let mut executor = PurtelExecutor::new(tasks, vec![vec![PurtelParamUsage::new("world", WRITE)]]);
+        executor.set_access_check_mode(PurtelAccessCheckMode::ENFORCE);
+        executor.calc_and_verify_exe_order();
+        assert!(executor.execute().is_ok());
+    }
+
+    #[test]
+    pub fn test_range_params() {
+        let param_usages = vec![
+            vec![PurtelParamUsage::with_range("data1", 0..1024, WRITE)],
+            vec![PurtelParamUsage::with_range("data1", 1024..2048, WRITE)],
+            // disjoint ranges in the same task are no duplicates
+            vec![PurtelParamUsage::with_range("data1", 1023..1024, READ), PurtelParamUsage::with_range("data1", 2048..2049, READ)],
+            // the whole parameter overlaps every range
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::with_range("data1", 0..0, WRITE)],
+        ];
+        let deps = PurtelExecutor::calc_task_dependencies(&param_usages);
+        assert_eq!(vec![vec![], vec![], vec![0], vec![0, 1], vec![3]], deps);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_range_params_duplicate_panic() {
+        let param_usages = vec![
+            vec![PurtelParamUsage::with_range("data1", 0..10, WRITE), PurtelParamUsage::with_range("data1", 9..20, READ)],
+        ];
+        PurtelExecutor::calc_task_dependencies(&param_usages);
+    }
+
+    #[test]
+    pub fn test_reduce() {
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("sum", WRITE)],
+            vec![PurtelParamUsage::new("sum", REDUCE)],
+            // Reduce after Reduce commutes
+            vec![PurtelParamUsage::new("sum", REDUCE)],
+            vec![PurtelParamUsage::new("sum", READ)],
+            vec![PurtelParamUsage::new("sum", REDUCE)],
+        ];
+        let deps = PurtelExecutor::calc_task_dependencies(&param_usages);
+        assert_eq!(vec![vec![], vec![0], vec![0], vec![0, 1, 2], vec![0, 3]], deps);
+
+        // tasks: reduce, reduce, reduce, read; the reader depends on all reducers
+        let reduction_tasks = |sum: &Arc<PurtelReduction<i32>>| (0..4)
+            .map(|i| {
+                let sum = sum.clone();
+                let task: Box<dyn FnOnce() + Send> = if i < 3 {
+                    Box::new(move || sum.reduce(|x| *x += 10))
+                } else {
+                    Box::new(move || assert_eq!(31, *sum.read()))
+                };
+                PurtelTask::new(task)
+            })
+            .collect::<Vec<_>>();
+        let reduce = || PurtelParamUsage::new("sum", REDUCE);
+
+        let sum = Arc::new(PurtelReduction::new("sum", 1, || 0, |acc: &mut i32, x| *acc += x));
+        let param_usages = vec![vec![reduce()], vec![reduce()], vec![reduce()], vec![PurtelParamUsage::new("sum", READ)]];
+        let mut executor = PurtelExecutor::new(reduction_tasks(&sum), param_usages);
+        executor.set_access_check_mode(PurtelAccessCheckMode::ENFORCE);
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+        assert_eq!(&[vec![0, 1, 2], vec![3]], report.exe_order());
+        assert_eq!(31, Arc::try_unwrap(sum).ok().unwrap().into_inner());
+
+        // the reader only declared REDUCE: it runs at the same time as the reducers
+        let sum = Arc::new(PurtelReduction::new("sum", 1, || 0, |acc: &mut i32, x| *acc += x));
+        let mut tasks = reduction_tasks(&sum);
+        let sum_t = sum.clone();
+        tasks[3] = PurtelTask::new(Box::new(move || drop(sum_t.read())));
+        let mut executor = PurtelExecutor::new(tasks, vec![vec![reduce()], vec![reduce()], vec![reduce()], vec![reduce()]]);
+        executor.set_access_check_mode(PurtelAccessCheckMode::ENFORCE);
+        executor.calc_and_verify_exe_order();
+        let err = executor.execute().unwrap_err();
+        match err {
+            PurtelError::UndeclaredAccess(violations) => {
+                assert_eq!(1, violations.len());
+                assert_eq!(Some(REDUCE), violations[0].declared());
+            },
+            _ => panic!("expected an undeclared access"),
+        }
+        assert_eq!(31, Arc::try_unwrap(sum).ok().unwrap().into_inner());
+    }
+
+    #[test]
+    pub fn test_reduce_across_executions() {
+        // the first execution has no reader, its private copy is still there when the task
+        // with the same ID of the second execution reduces
+        let sum = Arc::new(PurtelReduction::new("sum", 0, || 0, |acc: &mut i32, x| *acc += x));
+        let (sum_t1, sum_t2) = (sum.clone(), sum.clone());
+        let private_copies = Arc::new(std::sync::Mutex::new(vec![]));
+        let (private_copies_t1, private_copies_t2) = (private_copies.clone(), private_copies.clone());
+        let runs: Vec<Box<dyn FnOnce() + Send>> = vec![
+            Box::new(move || private_copies_t1.lock().unwrap().push(sum_t1.reduce(|x| { *x += 1; *x }))),
+            Box::new(move || private_copies_t2.lock().unwrap().push(sum_t2.reduce(|x| { *x += 2; *x }))),
+        ];
+        for task in runs {
+            let mut executor = PurtelExecutor::new(
+                vec![PurtelTask::new(task)],
+                vec![vec![PurtelParamUsage::new("sum", REDUCE)]],
+            );
+            executor.calc_and_verify_exe_order();
+            executor.execute().unwrap();
+        }
+        // each execution starts at the identity element
+        assert_eq!(vec![1, 2], *private_copies.lock().unwrap());
+        assert_eq!(3, *sum.read());
+    }
+
+    #[test]
+    pub fn test_exclusive() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("log", EXCLUSIVE)],
+            vec![PurtelParamUsage::new("log", EXCLUSIVE)],
+            // a child overlaps: same mutex
+            vec![PurtelParamUsage::new("log.file", EXCLUSIVE)],
+            vec![PurtelParamUsage::new("log", READ)],
+        ];
+        let deps = PurtelExecutor::calc_task_dependencies(&param_usages);
+        assert_eq!(vec![vec![], vec![], vec![], vec![0, 1, 2]], deps);
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let tasks = (0..4)
+            .map(|_| {
+                let running = running.clone();
+                let max_running = max_running.clone();
+                PurtelTask::new(Box::new(move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(std::time::Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                }))
+            })
+            .collect();
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+        // the exclusive tasks share a level but never run at the same time
+        assert_eq!(vec![vec![0, 1, 2], vec![3]], report.exe_order());
+        assert_eq!(1, max_running.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    pub fn test_renaming() {
+        let usages = || vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ), PurtelParamUsage::new("data2", WRITE)],
+            vec![PurtelParamUsage::new("data2", READ)],
+        ];
+        let deps = PurtelExecutor::calc_task_dependencies(&usages());
+        assert_eq!(vec![vec![], vec![0], vec![0, 1], vec![0, 2], vec![3]], deps);
+        // neither Write after Read nor Write after Write for data1
+        let deps = PurtelExecutor::calc_task_dependencies_with_renaming(&usages(), &["data1".to_owned()]);
+        assert_eq!(vec![vec![], vec![0], vec![], vec![0, 2], vec![3]], deps);
+
+        let data1 = Arc::new(PurtelVersioned::new("data1", 0));
+        let seen = Arc::new(std::sync::Mutex::new(vec![]));
+        let (data1_t0, data1_t1, data1_t2, data1_t3) = (data1.clone(), data1.clone(), data1.clone(), data1.clone());
+        let (seen_t1, seen_t3) = (seen.clone(), seen.clone());
+        let (written_sender, written_receiver) = std::sync::mpsc::channel();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || {
+                // the writer of task 2 runs at the same time and already created its version
+                written_receiver.recv_timeout(std::time::Duration::from_secs(10))
+                    .expect("task 2 must run at the same time");
+                data1_t0.replace(1);
+            })),
+            // must still see the version of task 0
+            PurtelTask::new(Box::new(move || seen_t1.lock().unwrap().push(*data1_t1.read()))),
+            PurtelTask::new(Box::new(move || {
+                data1_t2.replace(2);
+                written_sender.send(()).unwrap();
+            })),
+            PurtelTask::new(Box::new(move || seen_t3.lock().unwrap().push(*data1_t3.read()))),
+            PurtelTask::new(Box::new(|| {})),
+        ];
+        let mut executor = PurtelExecutor::new(tasks, usages());
+        executor.rename(&data1).unwrap();
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+        assert_eq!(vec![vec![0, 2], vec![1, 3], vec![4]], report.exe_order());
+        seen.lock().unwrap().sort();
+        assert_eq!(vec![1, 2], *seen.lock().unwrap());
+        assert_eq!(2, Arc::try_unwrap(data1).ok().unwrap().into_inner());
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_renaming_write_panics() {
+        // a writer of renamed data doesn't wait for the writer before it
+        let data1 = Arc::new(PurtelVersioned::new("data1", 0));
+        let data1_t = data1.clone();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || *data1_t.write() += 1)),
+        ];
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.rename(&data1).unwrap();
+        executor.calc_and_verify_exe_order();
+        executor.execute().unwrap();
+    }
+
+    #[test]
+    pub fn test_renaming_against_explicit_dependencies() {
+        let data1 = Arc::new(PurtelVersioned::new("data1", 0));
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+            vec![PurtelParamUsage::new("data1", WRITE)],
+        ];
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut executor = PurtelExecutor::new(logging_tasks(4, &log), param_usages);
+        executor.rename(&data1).unwrap();
+        // two readers see the same version in any order
+        executor.add_dependency(0, 1).unwrap();
+        executor.add_dependency(1, 2).unwrap();
+        // task 0 would run after the writer of task 3 (through tasks 1 and 2) but see the version before it
+        let err = executor.add_dependency(2, 3).unwrap_err();
+        assert_eq!(PurtelError::RenamingConflict("data1".to_owned(), 0, 3), err);
+        assert_eq!(
+            "task 0 runs after task 3 but the versions of the renamed parameter 'data1' are ordered by task ID",
+            err.to_string(),
+        );
+
+        // the same the other way round; without renaming the dependency is usually a
+        // cycle, but exclusive tasks may run in any order
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", EXCLUSIVE)],
+            vec![PurtelParamUsage::new("data1", EXCLUSIVE)],
+        ];
+        let mut executor = PurtelExecutor::new(logging_tasks(2, &log), param_usages);
+        executor.add_dependency(0, 1).unwrap();
+        assert_eq!(Err(PurtelError::RenamingConflict("data1".to_owned(), 0, 1)), executor.rename(&data1));
+        // renaming isn't enabled
+        executor.calc_and_verify_exe_order();
+        assert_eq!(Some(vec![vec![1], vec![0]]), executor.exe_order);
+    }
+
+    #[test]
+    pub fn test_explicit_dependencies() {
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", READ)],
+        ];
+        let mut executor = PurtelExecutor::new(logging_tasks(4, &log), param_usages);
+        // task 1 after task 2 although they share no data
+        executor.add_dependency(1, 2).unwrap();
+
+        // 0 -> 3 -> 1 -> 2 -> 0; not added
+        let err = executor.add_dependency(0, 3).unwrap_err();
+        assert_eq!(PurtelError::DependencyCycle(vec![0, 3, 1, 2]), err);
+        assert_eq!(
+            "the task dependencies contain a cycle: task 0 runs after task 3 runs after task 1 runs after task 2 runs after task 0",
+            err.to_string(),
+        );
+        assert_eq!(Err(PurtelError::DependencyCycle(vec![2])), executor.add_dependency(2, 2));
+
+        executor.set_execution_mode(PurtelExecutionMode::SEQUENTIAL_BY_ID);
+        executor.calc_and_verify_exe_order();
+        assert_eq!(Some(vec![vec![], vec![2], vec![0], vec![1]]), executor.task_deps);
+        let report = executor.execute().unwrap();
+        assert_eq!(&[vec![0], vec![2], vec![1], vec![3]], report.exe_order());
+        assert_eq!(vec![0, 2, 1, 3], log.lock().unwrap().iter().map(|(id, _)| *id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_aliases() {
+        let mut aliases = PurtelAliases::new();
+        aliases.alias("data1", "buffer");
+        assert_eq!("buffer", aliases.canonical("data1"));
+        assert_eq!("buffer.len", aliases.canonical("data1.len"));
+        assert_eq!("data10", aliases.canonical("data10"));
+
+        // the same `Arc` under two names; a clone of the handle is the same data
+        let data = Arc::new(PurtelShared::new("data1", vec![1]));
+        let other = Arc::new(PurtelShared::new("data2", vec![2]));
+        let mut registry = PurtelAliases::new();
+        registry.register("data1", &data);
+        registry.register("data2", &other);
+        registry.register("values", &data.clone());
+        assert_eq!("data1", registry.canonical("values"));
+        assert_eq!("data2", registry.canonical("data2"));
+
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("values", READ), PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", READ)],
+        ];
+        let data_t = data.clone();
+        let tasks = vec![
+            PurtelTask::new(Box::new(|| {})),
+            // accesses "data1" but declared the alias "values"
+            PurtelTask::new(Box::new(move || { let _ = data_t.read()[0]; })),
+            PurtelTask::new(Box::new(|| {})),
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.set_aliases(registry);
+        executor.set_access_check_mode(PurtelAccessCheckMode::ENFORCE);
+        executor.calc_and_verify_exe_order();
+        assert_eq!(Some(vec![vec![], vec![0], vec![]]), executor.task_deps);
+        assert!(executor.execute().is_ok());
+    }
+
+    #[test]
+    pub fn test_aliases_unregistered_handle() {
+        let data = Arc::new(PurtelShared::new("data1", 1));
+        let unknown = Arc::new(PurtelShared::new("data2", 2));
+        let mut registry = PurtelAliases::new();
+        registry.register("data1", &data);
+        assert!(registry.is_registry());
+
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", READ)],
+        ];
+        let data_t = data.clone();
+        let unknown_t = unknown.clone();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || { let _ = *data_t.read(); })),
+            // maybe an alias of "data1", the executor can't know
+            PurtelTask::new(Box::new(move || { let _ = *unknown_t.read(); })),
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.set_aliases(registry);
+        executor.calc_and_verify_exe_order();
+        match executor.execute() {
+            Err(PurtelError::UnregisteredHandle(accesses)) => {
+                assert_eq!(vec![PurtelAccess::new(1, "data2", READ)], accesses);
+            }
+            other => panic!("expected UnregisteredHandle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_shared_handle_guards() {
+        fn increment<H: PurtelSharedHandle<Target = i32>>(handle: &H) -> i32 {
+            *handle.write_guard() += 1;
+            let value = *handle.read_guard();
+            value
+        }
+        assert_eq!(2, increment(&Arc::new(std::sync::RwLock::new(1))));
+        assert_eq!(2, increment(&Arc::new(PurtelShared::new("data1", 1))));
+        assert_eq!(2, increment(&Arc::new(PurtelCell::new("data2", 1))));
+    }
+
+    #[test]
+    pub fn test_execute_static_schedule() {
+        const SCHEDULE: PurtelStaticSchedule = PurtelStaticSchedule::new(
+            &[&[], &[0], &[]],
+            &[&[0, 2], &[1]],
+        );
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+        ];
+        // the precomputed schedule is exactly what the runtime planning calculates
+        let deps = PurtelExecutor::calc_task_dependencies(&param_usages);
+        assert_eq!(SCHEDULE.task_deps(), deps.iter().map(|d| d.as_slice()).collect::<Vec<_>>().as_slice());
+
+        let mut executor = PurtelExecutor::with_schedule(logging_tasks(3, &log), param_usages, &SCHEDULE);
+        executor.set_execution_mode(PurtelExecutionMode::SEQUENTIAL_BY_LEVEL);
+        let report = executor.execute().unwrap();
+
+        assert_eq!(&[vec![0, 2], vec![1]], report.exe_order());
+        assert_eq!(vec![0, 2, 1], log.lock().unwrap().iter().map(|(id, _)| *id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_random_topological_order() {
+        let deps = vec![
+            vec![],
+            vec![],
+            vec![0],
+            vec![1],
+            vec![],
+            vec![],
+            vec![2, 0, 3, 1, 4, 5],
+        ];
+        let order = random_topological_order(&deps, &mut FuzzRng::new(42));
+        assert_eq!(order, random_topological_order(&deps, &mut FuzzRng::new(42)), "same seed, same order");
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6], sorted, "every task exactly once");
+        for (pos, task_id) in order.iter().enumerate() {
+            for dep in &deps[*task_id] {
+                assert!(order[..pos].contains(dep), "task {} must come after its dependency {}", task_id, dep);
+            }
+        }
+
+        // 20 independent tasks; different seeds must not always produce the same order
+        let deps = vec![vec![]; 20];
+        let orders = (0..5)
+            .map(|seed| random_topological_order(&deps, &mut FuzzRng::new(seed)))
+            .collect::<Vec<_>>();
+        assert!(orders.iter().any(|o| o != &orders[0]));
+    }
+
+    #[test]
+    pub fn test_execute_fuzz() {
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", WRITE)],
+            vec![PurtelParamUsage::new("data1", READ)],
+            vec![PurtelParamUsage::new("data2", WRITE)],
+            vec![PurtelParamUsage::new("data1", WRITE), PurtelParamUsage::new("data2", WRITE)],
+        ];
+        let config = PurtelFuzzConfig::new(1337).with_max_delay(std::time::Duration::from_millis(5));
+        let mut executor = PurtelExecutor::new(logging_tasks(4, &log), param_usages);
+        executor.set_execution_mode(PurtelExecutionMode::FUZZ(config));
+        executor.calc_and_verify_exe_order();
+        let report = executor.execute().unwrap();
+
+        assert_eq!(PurtelExecutionMode::FUZZ(config), report.mode(), "seed must be reported");
+        assert_eq!(Some(1337), report.seed());
+        assert_eq!(4, report.exe_order().len());
+        let log = log.lock().unwrap().iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let pos = |task_id| log.iter().position(|id| *id == task_id).unwrap();
+        assert!(pos(0) < pos(1));
+        assert!(pos(1) < pos(3));
+        assert!(pos(2) < pos(3));
+    }
+
+    /// State for the differential check tests.
+    struct DiffState {
+        data1: Arc<std::sync::RwLock<i32>>,
+        data2: Arc<std::sync::RwLock<i32>>,
+    }
+
+    impl PurtelDiffState for DiffState {
+        fn deep_clone(&self) -> Self {
+            Self {
+                data1: self.data1.deep_clone(),
+                data2: self.data2.deep_clone(),
+            }
+        }
+
+        fn param_identifiers(&self) -> Vec<String> {
+            vec!["data1".to_owned(), "data2".to_owned()]
+        }
+
+        fn param_eq(&self, other: &Self, identifier: &str) -> bool {
+            match identifier {
+                "data1" => self.data1.value_eq(&other.data1),
+                "data2" => self.data2.value_eq(&other.data2),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Task 0 writes data1, task 1 copies data1 into data2. If task 1 doesn't
+    /// declare that it reads data1 both end up in the same level. With `race`
+    /// task 0 waits until task 1 read data1; only possible if both run at the same time.
+    fn diff_executor(state: &DiffState, declare_data1: bool, race: bool) -> PurtelExecutor {
+        let data1_t0 = state.data1.clone();
+        let data1_t1 = state.data1.clone();
+        let data2_t1 = state.data2.clone();
+        let (read_sender, read_receiver) = std::sync::mpsc::channel();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || {
+                if race {
+                    read_receiver.recv_timeout(std::time::Duration::from_secs(10))
+                        .expect("task 1 must run at the same time");
+                }
+                *data1_t0.write().unwrap() = 42;
+            })),
+            PurtelTask::new(Box::new(move || {
+                let value = *data1_t1.read().unwrap();
+                *data2_t1.write().unwrap() = value;
+                // nobody listens without `race`
+                let _ = read_sender.send(());
+            })),
+        ];
+        let mut task1_usages = vec![PurtelParamUsage::new("data2", WRITE)];
+        if declare_data1 {
+            task1_usages.push(PurtelParamUsage::new("data1", READ));
+        }
+        PurtelExecutor::new(tasks, vec![vec![PurtelParamUsage::new("data1", WRITE)], task1_usages])
+    }
+
+    #[test]
+    pub fn test_differential_check() {
+        let state = DiffState {
+            data1: Arc::new(std::sync::RwLock::new(1)),
+            data2: Arc::new(std::sync::RwLock::new(0)),
+        };
+        assert_eq!(Ok(()), purtel_differential_check(&state, |s| diff_executor(s, true, false)));
+        // the harness builds the sequential run first and the parallel one second
+        let builds = std::cell::Cell::new(0);
+        let build = |s: &DiffState| {
+            builds.set(builds.get() + 1);
+            diff_executor(s, false, builds.get() == 2)
+        };
+        assert_eq!(
+            Err(PurtelError::StateMismatch("data2".to_owned())),
+            purtel_differential_check(&state, build)
+        );
+        // initial state is untouched
+        assert_eq!(1, *state.data1.read().unwrap());
+        assert_eq!(0, *state.data2.read().unwrap());
+    }
+
+    #[test]
+    pub fn test_diff_params() {
+        // a deep copy is equal to the original but independent of it
+        let slice = Arc::new(PurtelSlice::new("data1", vec![1, 2]));
+        let slice_copy = slice.deep_clone();
+        assert!(slice.value_eq(&slice_copy));
+        slice_copy.write(1..2)[0] = 3;
+        assert!(!slice.value_eq(&slice_copy));
+
+        let sum = Arc::new(PurtelReduction::new("sum", 1, || 0, |acc: &mut i32, x| *acc += x));
+        let sum_copy = sum.deep_clone();
+        assert!(sum.value_eq(&sum_copy));
+        sum_copy.reduce(|x| *x += 2);
+        assert!(!sum.value_eq(&sum_copy));
+        assert_eq!(3, *sum_copy.read());
+
+        let versioned = Arc::new(PurtelVersioned::new("data2", 1));
+        let versioned_copy = versioned.deep_clone();
+        assert!(versioned.value_eq(&versioned_copy));
+        versioned_copy.replace(2);
+        assert!(!versioned.value_eq(&versioned_copy));
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    pub fn test_purtel_cell_undeclared_write_panics() {
+        let data1 = Arc::new(PurtelCell::new("data1", 0));
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || *data1.write() += 1)),
+        ];
+        let param_usages = vec![
+            vec![PurtelParamUsage::new("data1", READ)],
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.calc_and_verify_exe_order();
+        executor.execute().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "is written while it is accessed")]
+    pub fn test_purtel_cell_conflicting_borrow_panics() {
+        // also in release builds; safe code must not get `&T` and `&mut T` at the same time
+        let data1 = PurtelCell::new("data1", 0);
+        let _read = data1.read();
+        let _write = data1.write();
+    }
+
+    #[test]
+    pub fn test_purtel_cell_concurrent_borrows() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        // readers and writers race for the cell; every access either panics or has the
+        // cell for itself (writer) respectively shares it with readers only
+        let data1 = Arc::new(PurtelCell::new("data1", 0));
+        let writing = Arc::new(AtomicBool::new(false));
+        let threads = (0..4).map(|i| {
+            let data1 = data1.clone();
+            let writing = writing.clone();
+            std::thread::spawn(move || {
+                for j in 0..5000 {
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        if (i + j) % 2 == 0 {
+                            let mut value = data1.write();
+                            assert!(!writing.swap(true, Ordering::SeqCst), "two writers at the same time");
+                            *value += 1;
+                            std::thread::yield_now();
+                            writing.store(false, Ordering::SeqCst);
+                        } else {
+                            let _value = data1.read();
+                            assert!(!writing.load(Ordering::SeqCst), "reader and writer at the same time");
+                            std::thread::yield_now();
+                        }
+                    }));
+                    if let Err(e) = result {
+                        let message = e.downcast_ref::<String>().cloned().unwrap_or_default();
+                        assert!(message.contains("The schedule was violated"), "{}", message);
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        // the counter is back to unused
+        *data1.write() += 1;
+    }
+
+    #[test]
+    pub fn test_purtel_slice_disjoint_ranges() {
+        let data1 = Arc::new(PurtelSlice::new("data1", vec![0; 4]));
+        let param_usages = vec![
+            vec![PurtelParamUsage::with_range("data1", 0..2, WRITE)],
+            vec![PurtelParamUsage::with_range("data1", 2..4, WRITE)],
+        ];
+        // each task holds its range until the other one holds its range as well;
+        // this only finishes if both tasks run at the same time
+        let (first_sender, first_receiver) = channel();
+        let (second_sender, second_receiver) = channel();
+        let data1_t = data1.clone();
+        let data1_tt = data1.clone();
+        let tasks = vec![
+            PurtelTask::new(Box::new(move || {
+                let mut values = data1_t.write(0..2);
+                values.fill(1);
+                first_sender.send(()).unwrap();
+                second_receiver.recv_timeout(std::time::Duration::from_secs(10))
+                    .expect("The tasks of disjoint ranges must run at the same time!");
+            })),
+            PurtelTask::new(Box::new(move || {
+                let mut values = data1_tt.write(2..4);
+                values.fill(2);
+                first_receiver.recv_timeout(std::time::Duration::from_secs(10))
+                    .expect("The tasks of disjoint ranges must run at the same time!");
+                second_sender.send(()).unwrap();
+            })),
+        ];
+        let mut executor = PurtelExecutor::new(tasks, param_usages);
+        executor.calc_and_verify_exe_order();
+        assert_eq!(Some(vec![vec![0, 1]]), executor.exe_order);
+        executor.execute().unwrap();
+        assert_eq!(vec![1, 1, 2, 2], Arc::try_unwrap(data1).ok().unwrap().into_inner());
+    }
+
+    #[test]
+    #[should_panic(expected = "is accessed by two tasks at the same time")]
+    pub fn test_purtel_slice_overlapping_borrow_panics() {
+        // also in release builds; overlapping ranges conflict if one of them is written
+        let data1 = PurtelSlice::new("data1", vec![0; 4]);
+        let _read = data1.read(0..2);
+        let _other_read = data1.read(1..3);
+        let _write = data1.write(1..2);
+    }
+
 }
 
 
@@ -0,0 +1,23 @@
+//! Different strategies how `PurtelExecutor` runs the tasks.
+
+use crate::fuzz::PurtelFuzzConfig;
+
+/// Describes how `PurtelExecutor::execute()` runs the tasks. All modes respect the
+/// dependencies between tasks and produce the same `PurtelExecutionReport`.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PurtelExecutionMode {
+    /// All tasks of an execution level run in parallel, each on its own thread. Default.
+    PARALLEL,
+    /// All tasks run on the calling thread strictly in `TaskId` order (a task with an
+    /// explicit dependency on a later task waits for it). No threads are created. Useful
+    /// to rule out concurrency when debugging logic errors.
+    SEQUENTIAL_BY_ID,
+    /// All tasks run on the calling thread in the order of the calculated execution
+    /// levels. No threads are created.
+    SEQUENTIAL_BY_LEVEL,
+    /// Test mode: tasks start in a random topological order derived from the seed, each
+    /// as soon as all of its dependencies are finished, optionally after a random delay.
+    /// Running a graph with many seeds surfaces missing read/write declarations.
+    FUZZ(PurtelFuzzConfig),
+}
@@ -0,0 +1,180 @@
+//! Reductions. Tasks that only combine values into shared data (e.g. add to a counter or a
+//! histogram) commute, hence they don't need to run after each other. Each of them updates
+//! a private copy; the copies are combined with the shared value before the next task reads it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::PurtelParamUsageKind;
+use crate::PurtelParamUsageKind::{READ, REDUCE, WRITE};
+use crate::context::with_current_task;
+use crate::types::TaskId;
+
+/// The private copies of the reducing tasks of one execution.
+struct PurtelPartials<T> {
+    /// ID of the execution that created the private copies
+    execution_id: u64,
+    /// task ID => private copy
+    by_task: HashMap<TaskId, T>,
+}
+
+/// Shared data for tasks with the parameter usage `REDUCE`. `reduce()` gives the running task
+/// a private copy that starts at the identity element; `read()` and `write()` first combine
+/// all private copies into the shared value. `PurtelExecutor` lets all `REDUCE` tasks of the
+/// same parameter run concurrently but a task that reads or writes the parameter only after
+/// all of them finished, hence it sees the combined value.
+///
+/// The operator must be associative and commutative; the private copies are combined in
+/// an unspecified order.
+///
+/// The executor doesn't take part in the reduction: the private copies are kept per task ID
+/// and combined lazily by the next `read()`, `write()` or `into_inner()`. The dependencies
+/// guarantee that a reading task only runs after all reducing tasks finished. The private
+/// copies of an earlier execution (e.g. without a reader after the reducers) are combined
+/// when a task of the next execution accesses the data, so a task never continues the
+/// private copy of a task with the same ID of another execution.
+///
+/// Like `PurtelShared` every access is reported to the executor.
+///
+/// ```ignore
+/// let sum = Arc::new(PurtelReduction::new("sum", 0, || 0, |acc, x| *acc += x));
+/// // inside a task declared with `reduce(sum)`
+/// sum.reduce(|local| *local += 42);
+/// ```
+#[allow(clippy::type_complexity)]
+pub struct PurtelReduction<T> {
+    identifier: String,
+    value: RwLock<T>,
+    /// private copies of the tasks that haven't been combined yet
+    partials: Mutex<PurtelPartials<T>>,
+    identity: Arc<dyn Fn() -> T + Send + Sync>,
+    combine: Arc<dyn Fn(&mut T, T) + Send + Sync>,
+}
+
+impl<T> PurtelReduction<T> {
+
+    /// Constructor. `identifier` must match the identifier that tasks use in their
+    /// `PurtelParamUsage` for this data. `identity` creates the initial private copy of a
+    /// task and `combine` merges a private copy into the shared value.
+    pub fn new(identifier: &str,
+               value: T,
+               identity: impl Fn() -> T + Send + Sync + 'static,
+               combine: impl Fn(&mut T, T) + Send + Sync + 'static) -> Self {
+        Self {
+            identifier: identifier.to_owned(),
+            value: RwLock::new(value),
+            partials: Mutex::new(PurtelPartials {
+                execution_id: 0,
+                by_task: HashMap::new(),
+            }),
+            identity: Arc::new(identity),
+            combine: Arc::new(combine),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Reduction with the same identifier and operator but another value.
+    pub(crate) fn with_value(&self, value: T) -> Self {
+        Self {
+            identifier: self.identifier.clone(),
+            value: RwLock::new(value),
+            partials: Mutex::new(PurtelPartials {
+                execution_id: 0,
+                by_task: HashMap::new(),
+            }),
+            identity: self.identity.clone(),
+            combine: self.combine.clone(),
+        }
+    }
+
+    /// Updates the private copy of the running task. Reports a `REDUCE` access. Outside of a
+    /// purtel task the update is combined into the shared value right away.
+    pub fn reduce<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let running = with_current_task(|ctx| ctx.map(|ctx| {
+            ctx.record_access(&self.identifier, REDUCE, self as *const Self as *const ());
+            (ctx.execution_id(), ctx.task_id())
+        }));
+        let (execution_id, task_id) = match running {
+            Some(running) => running,
+            None => {
+                let mut partial = (self.identity)();
+                let result = f(&mut partial);
+                (self.combine)(&mut self.write_value(), partial);
+                return result;
+            }
+        };
+        // the lock isn't held while `f` runs, other reducers can continue
+        let partial = self.start_execution(execution_id).by_task.remove(&task_id);
+        let mut partial = partial.unwrap_or_else(|| (self.identity)());
+        let result = f(&mut partial);
+        self.lock_partials().by_task.insert(task_id, partial);
+        result
+    }
+
+    /// Shared access to the combined value. Reports a `READ` access of the running task.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.report(READ);
+        self.combine_partials();
+        self.value.read().expect("PurtelReduction lock is poisoned!")
+    }
+
+    /// Exclusive access to the combined value. Reports a `WRITE` access of the running task.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.report(WRITE);
+        self.combine_partials();
+        self.write_value()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.combine_partials();
+        self.value.into_inner().expect("PurtelReduction lock is poisoned!")
+    }
+
+    /// Combines all private copies into the shared value.
+    fn combine_partials(&self) {
+        let partials = std::mem::take(&mut self.lock_partials().by_task);
+        self.combine_into_value(partials);
+    }
+
+    fn combine_into_value(&self, partials: HashMap<TaskId, T>) {
+        if partials.is_empty() {
+            return;
+        }
+        let mut value = self.write_value();
+        for (_, partial) in partials {
+            (self.combine)(&mut value, partial);
+        }
+    }
+
+    /// Locks the private copies. In a task of a new execution the private copies of the
+    /// previous execution are combined first.
+    fn start_execution(&self, execution_id: u64) -> MutexGuard<'_, PurtelPartials<T>> {
+        let mut partials = self.lock_partials();
+        if partials.execution_id != execution_id {
+            partials.execution_id = execution_id;
+            let previous = std::mem::take(&mut partials.by_task);
+            drop(partials);
+            self.combine_into_value(previous);
+            partials = self.lock_partials();
+        }
+        partials
+    }
+
+    fn write_value(&self) -> RwLockWriteGuard<'_, T> {
+        self.value.write().expect("PurtelReduction lock is poisoned!")
+    }
+
+    fn lock_partials(&self) -> MutexGuard<'_, PurtelPartials<T>> {
+        self.partials.lock().expect("PurtelReduction lock is poisoned!")
+    }
+
+    fn report(&self, kind: PurtelParamUsageKind) {
+        with_current_task(|ctx| {
+            if let Some(ctx) = ctx {
+                ctx.record_access(&self.identifier, kind, self as *const Self as *const ());
+            }
+        });
+    }
+}
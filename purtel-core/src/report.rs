@@ -0,0 +1,51 @@
+//! Summary of a finished execution.
+
+use crate::access::PurtelAccess;
+use crate::mode::PurtelExecutionMode;
+use crate::types::TaskExecutionLevel;
+
+/// Returned by `PurtelExecutor::execute()` after all tasks finished.
+#[derive(Debug, Clone)]
+pub struct PurtelExecutionReport {
+    mode: PurtelExecutionMode,
+    exe_order: Vec<TaskExecutionLevel>,
+    accesses: Vec<PurtelAccess>,
+}
+
+impl PurtelExecutionReport {
+    pub(crate) fn new(mode: PurtelExecutionMode,
+                      exe_order: Vec<TaskExecutionLevel>,
+                      accesses: Vec<PurtelAccess>) -> Self {
+        Self {
+            mode,
+            exe_order,
+            accesses,
+        }
+    }
+
+    /// The mode the tasks were executed with.
+    pub fn mode(&self) -> PurtelExecutionMode {
+        self.mode
+    }
+
+    /// The seed of mode `FUZZ`; reproduces the order with `PurtelFuzzConfig::new(seed)`.
+    pub fn seed(&self) -> Option<u64> {
+        match self.mode {
+            PurtelExecutionMode::FUZZ(config) => Some(config.seed()),
+            _ => None,
+        }
+    }
+
+    /// The execution levels in the order they were executed. In mode
+    /// `SEQUENTIAL_BY_ID` each task forms its own level. In mode `FUZZ` each
+    /// task forms its own level too and the order is the start order.
+    pub fn exe_order(&self) -> &[TaskExecutionLevel] {
+        &self.exe_order
+    }
+
+    /// All accesses reported by instrumented shared data. Empty
+    /// if `PurtelAccessCheckMode::OFF` was used.
+    pub fn accesses(&self) -> &[PurtelAccess] {
+        &self.accesses
+    }
+}
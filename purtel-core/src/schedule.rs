@@ -0,0 +1,34 @@
+//! Precomputed schedules. `#[purtel_tasks]` sees all parameter usages at compile time and
+//! runs the same dependency analysis as `PurtelExecutor::calc_and_verify_exe_order()`
+//! during macro expansion. The result is emitted as a constant of this type.
+
+use crate::types::TaskId;
+
+/// Dependencies and execution levels of all tasks, calculated ahead of time.
+/// See `PurtelExecutor::with_schedule()`.
+#[derive(Debug, Copy, Clone)]
+pub struct PurtelStaticSchedule {
+    task_deps: &'static [&'static [TaskId]],
+    exe_order: &'static [&'static [TaskId]],
+}
+
+impl PurtelStaticSchedule {
+
+    /// Constructor. `task_deps` as calculated by `PurtelExecutor::calc_task_dependencies()`
+    /// and `exe_order` as calculated by `PurtelExecutor::calc_execution_levels()`.
+    pub const fn new(task_deps: &'static [&'static [TaskId]],
+                     exe_order: &'static [&'static [TaskId]]) -> Self {
+        Self {
+            task_deps,
+            exe_order,
+        }
+    }
+
+    pub fn task_deps(&self) -> &'static [&'static [TaskId]] {
+        self.task_deps
+    }
+
+    pub fn exe_order(&self) -> &'static [&'static [TaskId]] {
+        self.exe_order
+    }
+}
@@ -0,0 +1,190 @@
+//! Shared slice whose elements are partitioned between tasks, e.g. chunks of a vector that
+//! separate tasks process in parallel.
+
+use std::cell::UnsafeCell;
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::Mutex;
+#[cfg(debug_assertions)]
+use crate::context::with_current_task;
+#[cfg(debug_assertions)]
+use crate::PurtelParamUsageKind::{EXCLUSIVE, REDUCE, WRITE};
+
+/// Like `PurtelCell`, but a task borrows only the half-open range of elements it declared,
+/// e.g. `#[purtel_task(write(data1[0..1024]))]` and `data1.write(0..1024)`. Tasks with
+/// disjoint ranges run in parallel and access their elements at the same time without a
+/// lock; with `PurtelCell` or `RwLock` they would conflict on the whole data.
+///
+/// The borrowed ranges are tracked (also in release builds): a borrow that conflicts with
+/// another one at the same time panics instead of causing a data race. Debug builds
+/// additionally check that the running task declared the parameter for the whole range.
+///
+/// Use it like `Arc<RwLock<Vec<T>>>`: `Arc::new(PurtelSlice::new("data1", vec![0; 2048]))`.
+/// As `PurtelSharedHandle` (e.g. with `guards`) it borrows all elements.
+pub struct PurtelSlice<T> {
+    identifier: String,
+    values: Box<[UnsafeCell<T>]>,
+    /// the ranges that are borrowed right now; `true` for a writer
+    borrows: Mutex<Vec<(Range<usize>, bool)>>,
+}
+
+// Access is synchronized by the execution levels of the executor; `borrows` turns
+// a violation of the schedule into a panic.
+unsafe impl<T: Send> Send for PurtelSlice<T> {}
+unsafe impl<T: Send + Sync> Sync for PurtelSlice<T> {}
+
+impl<T> PurtelSlice<T> {
+
+    /// Constructor. `identifier` must match the identifier that tasks use in their
+    /// `PurtelParamUsage` for this data.
+    pub fn new(identifier: &str, values: Vec<T>) -> Self {
+        Self {
+            identifier: identifier.to_owned(),
+            values: values.into_iter().map(UnsafeCell::new).collect(),
+            borrows: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Shared access to the elements `range`. The running task must have declared the
+    /// identifier (or a range that contains `range`) as `READ` or `WRITE`.
+    pub fn read(&self, range: Range<usize>) -> PurtelSliceRef<'_, T> {
+        #[cfg(debug_assertions)]
+        self.check_declaration(&range, false);
+        self.borrow(&range, false);
+        // the borrow guarantees that there is no writer of the range until the reference is dropped
+        let values = unsafe { std::slice::from_raw_parts(self.range_ptr(&range), range.len()) };
+        PurtelSliceRef { slice: self, range, values }
+    }
+
+    /// Exclusive access to the elements `range`. The running task must have declared the
+    /// identifier (or a range that contains `range`) as `WRITE` or `EXCLUSIVE`.
+    pub fn write(&self, range: Range<usize>) -> PurtelSliceRefMut<'_, T> {
+        #[cfg(debug_assertions)]
+        self.check_declaration(&range, true);
+        self.borrow(&range, true);
+        // the borrow guarantees that there is no other reference to the range until this one is dropped
+        let values = unsafe { std::slice::from_raw_parts_mut(self.range_ptr(&range), range.len()) };
+        PurtelSliceRefMut { slice: self, range, values }
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.values.into_vec().into_iter().map(UnsafeCell::into_inner).collect()
+    }
+
+    /// Pointer to the first element of `range`.
+    fn range_ptr(&self, range: &Range<usize>) -> *mut T {
+        UnsafeCell::raw_get(self.values[range.clone()].as_ptr())
+    }
+
+    /// Registers the borrow of `range`; panics if it conflicts with another borrow.
+    fn borrow(&self, range: &Range<usize>, write: bool) {
+        assert!(range.start <= range.end && range.end <= self.values.len(),
+                "Range {:?} is out of bounds of PurtelSlice '{}' with length {}!",
+                range, self.identifier, self.values.len());
+        let mut borrows = self.lock_borrows();
+        let conflict = borrows.iter()
+            .any(|(other, other_write)| (write || *other_write) && range.start < other.end && other.start < range.end);
+        if conflict {
+            // don't poison the lock; the panic is the error of the task
+            drop(borrows);
+            panic!("PurtelSlice '{}' range {:?} is accessed by two tasks at the same time! The schedule was violated.",
+                   self.identifier, range);
+        }
+        borrows.push((range.clone(), write));
+    }
+
+    fn release(&self, range: &Range<usize>, write: bool) {
+        let mut borrows = self.lock_borrows();
+        let i = borrows.iter().position(|(other, other_write)| other == range && *other_write == write)
+            .expect("Released range must be borrowed");
+        borrows.swap_remove(i);
+    }
+
+    fn lock_borrows(&self) -> std::sync::MutexGuard<'_, Vec<(Range<usize>, bool)>> {
+        self.borrows.lock().expect("PurtelSlice lock is poisoned!")
+    }
+
+    /// Panics if the task that runs on the current thread didn't declare the usage of
+    /// `range`. Accesses from outside of a purtel task are not checked.
+    #[cfg(debug_assertions)]
+    fn check_declaration(&self, range: &Range<usize>, write: bool) {
+        with_current_task(|ctx| {
+            if let Some(ctx) = ctx {
+                match ctx.declared_range_kind(&self.identifier, range) {
+                    None => panic!(
+                        "Task {} accesses range {:?} of PurtelSlice '{}' but didn't declare it!",
+                        ctx.task_id(), range, self.identifier
+                    ),
+                    Some(kind) if write && kind != WRITE && kind != EXCLUSIVE => panic!(
+                        "Task {} writes range {:?} of PurtelSlice '{}' but only declared {:?}!",
+                        ctx.task_id(), range, self.identifier, kind
+                    ),
+                    Some(REDUCE) => panic!(
+                        "Task {} reads range {:?} of PurtelSlice '{}' but only declared REDUCE!",
+                        ctx.task_id(), range, self.identifier
+                    ),
+                    _ => {}
+                }
+            }
+        });
+    }
+}
+
+/// Shared reference to a range of a `PurtelSlice`. Returned by `PurtelSlice::read()`.
+pub struct PurtelSliceRef<'a, T> {
+    slice: &'a PurtelSlice<T>,
+    range: Range<usize>,
+    values: &'a [T],
+}
+
+impl<T> Deref for PurtelSliceRef<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.values
+    }
+}
+
+impl<T> Drop for PurtelSliceRef<'_, T> {
+    fn drop(&mut self) {
+        self.slice.release(&self.range, false);
+    }
+}
+
+/// Exclusive reference to a range of a `PurtelSlice`. Returned by `PurtelSlice::write()`.
+pub struct PurtelSliceRefMut<'a, T> {
+    slice: &'a PurtelSlice<T>,
+    range: Range<usize>,
+    values: &'a mut [T],
+}
+
+impl<T> Deref for PurtelSliceRefMut<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.values
+    }
+}
+
+impl<T> DerefMut for PurtelSliceRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.values
+    }
+}
+
+impl<T> Drop for PurtelSliceRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.slice.release(&self.range, true);
+    }
+}
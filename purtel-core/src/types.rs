@@ -7,7 +7,8 @@ pub type TaskId = usize;
 /// Defines the dependencies of a task. A task is dependent on `n` other task IDs.
 /// A task can only be dependent on tasks with an ID `< curr_task_id`. In other words
 /// a task can only be dependent on tasks that are defined "higher" (at lower index)
-/// in the vector with all closures.
+/// in the vector with all closures. Only explicit dependencies
+/// (`PurtelExecutor::add_dependency()`) can point to any task as long as there is no cycle.
 pub type TaskDependencies = Vec<TaskId>;
 
 /// Defines which task IDs should execute per iteration level. All tasks inside the
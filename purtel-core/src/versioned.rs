@@ -0,0 +1,189 @@
+//! Data versioning (renaming) like in StarSs/OmpSs. A writer gets a fresh copy of the parameter
+//! while tasks that read it before (in the order of the task IDs) keep the old version. This
+//! makes the Write-after-Read and Write-after-Write dependencies unnecessary, see
+//! `PurtelExecutor::rename()`.
+
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::PurtelParamUsageKind;
+use crate::PurtelParamUsageKind::{READ, WRITE};
+use crate::context::with_current_task;
+use crate::types::TaskId;
+
+/// All versions of the data of one execution.
+struct PurtelVersions<T> {
+    /// ID of the execution that created the versions in `by_writer`
+    execution_id: u64,
+    /// the version before the execution
+    initial: Arc<T>,
+    /// task ID of the writer => version it wrote
+    by_writer: BTreeMap<TaskId, Arc<T>>,
+}
+
+impl<T> PurtelVersions<T> {
+
+    /// The version that a task sees: the one of the last writer up to `task_id`.
+    fn visible(&self, task_id: TaskId) -> Arc<T> {
+        self.by_writer.range(..=task_id)
+            .next_back()
+            .map(|(_, version)| version.clone())
+            .unwrap_or_else(|| self.initial.clone())
+    }
+
+    /// The last version; drops all other ones.
+    fn collapse(&mut self) {
+        if let Some((_, latest)) = self.by_writer.pop_last() {
+            self.initial = latest;
+        }
+        self.by_writer.clear();
+    }
+}
+
+/// Shared data that supports renaming. Each task sees the version of the last task before it
+/// (in the order of the task IDs) that wrote it; `replace()` creates a new version and
+/// `write()` works on a copy that becomes a new version when the guard is dropped. Hence a
+/// writer never affects a task that comes before it, even if they run at the same time.
+/// Renamed data (`PurtelExecutor::rename()`) must be written with `replace()`.
+///
+/// Like `PurtelShared` every access is reported to the executor.
+///
+/// Use it like `Arc<RwLock<T>>`: `Arc::new(PurtelVersioned::new("data1", vec![1, 2, 3]))`.
+pub struct PurtelVersioned<T> {
+    identifier: String,
+    versions: Mutex<PurtelVersions<T>>,
+}
+
+impl<T: Clone> PurtelVersioned<T> {
+
+    /// Constructor. `identifier` must match the identifier that tasks use in their
+    /// `PurtelParamUsage` for this data.
+    pub fn new(identifier: &str, value: T) -> Self {
+        Self {
+            identifier: identifier.to_owned(),
+            versions: Mutex::new(PurtelVersions {
+                execution_id: 0,
+                initial: Arc::new(value),
+                by_writer: BTreeMap::new(),
+            }),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// The version of the running task (the last one outside of a purtel task).
+    /// Reports a `READ` access.
+    pub fn read(&self) -> Arc<T> {
+        let running = self.report(READ);
+        let versions = self.lock_versions(running.map(|(execution_id, _)| execution_id));
+        versions.visible(running.map(|(_, task_id)| task_id).unwrap_or(TaskId::MAX))
+    }
+
+    /// Copy of the version of the running task that becomes the version of the task when the
+    /// guard is dropped. Outside of a purtel task it replaces all versions. Reports a `WRITE` access.
+    ///
+    /// Panics inside a task if the data is renamed: the writers don't depend on each other
+    /// then, so the previous version may not exist yet. Use `replace()` instead.
+    pub fn write(&self) -> PurtelVersionMut<'_, T> {
+        let renamed = with_current_task(|ctx| ctx.is_some_and(|ctx| ctx.is_renamed(&self.identifier)));
+        if renamed {
+            panic!("PurtelVersioned '{}' is renamed and write() would read the version of a writer that may not have finished! Use replace().",
+                   self.identifier);
+        }
+        let running = self.report(WRITE);
+        let mut versions = self.lock_versions(running.map(|(execution_id, _)| execution_id));
+        if running.is_none() {
+            versions.collapse();
+        }
+        let task_id = running.map(|(_, task_id)| task_id);
+        let value = versions.visible(task_id.unwrap_or(TaskId::MAX));
+        PurtelVersionMut {
+            versioned: self,
+            task_id,
+            value: Some(T::clone(&value)),
+        }
+    }
+
+    /// Makes `value` the version of the running task without reading the previous version.
+    /// Outside of a purtel task it replaces all versions. Reports a `WRITE` access.
+    pub fn replace(&self, value: T) {
+        let running = self.report(WRITE);
+        let mut versions = self.lock_versions(running.map(|(execution_id, _)| execution_id));
+        match running {
+            Some((_, task_id)) => {
+                versions.by_writer.insert(task_id, Arc::new(value));
+            },
+            None => {
+                versions.by_writer.clear();
+                versions.initial = Arc::new(value);
+            },
+        }
+    }
+
+    /// The last version.
+    pub fn into_inner(self) -> T {
+        let mut versions = self.versions.into_inner().expect("PurtelVersioned lock is poisoned!");
+        versions.collapse();
+        Arc::try_unwrap(versions.initial).unwrap_or_else(|version| T::clone(&version))
+    }
+
+    /// Locks the versions. Inside a task of a new execution the versions of the previous
+    /// execution are dropped, except for the last one.
+    fn lock_versions(&self, execution_id: Option<u64>) -> MutexGuard<'_, PurtelVersions<T>> {
+        let mut versions = self.versions.lock().expect("PurtelVersioned lock is poisoned!");
+        if let Some(execution_id) = execution_id {
+            if versions.execution_id != execution_id {
+                versions.collapse();
+                versions.execution_id = execution_id;
+            }
+        }
+        versions
+    }
+
+    /// Reports the access and returns the execution and the task ID of the running task.
+    fn report(&self, kind: PurtelParamUsageKind) -> Option<(u64, TaskId)> {
+        with_current_task(|ctx| {
+            ctx.map(|ctx| {
+                ctx.record_access(&self.identifier, kind, self as *const Self as *const ());
+                (ctx.execution_id(), ctx.task_id())
+            })
+        })
+    }
+}
+
+/// Exclusive access to a new version of a `PurtelVersioned`. Returned by `PurtelVersioned::write()`.
+pub struct PurtelVersionMut<'a, T: Clone> {
+    versioned: &'a PurtelVersioned<T>,
+    /// writer of the version; `None` outside of a purtel task
+    task_id: Option<TaskId>,
+    value: Option<T>,
+}
+
+impl<T: Clone> Deref for PurtelVersionMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().expect("Must have value")
+    }
+}
+
+impl<T: Clone> DerefMut for PurtelVersionMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().expect("Must have value")
+    }
+}
+
+impl<T: Clone> Drop for PurtelVersionMut<'_, T> {
+    fn drop(&mut self) {
+        let value = Arc::new(self.value.take().expect("Must have value"));
+        let mut versions = self.versioned.versions.lock().expect("PurtelVersioned lock is poisoned!");
+        match self.task_id {
+            Some(task_id) => {
+                versions.by_writer.insert(task_id, value);
+            },
+            None => versions.initial = value,
+        }
+    }
+}
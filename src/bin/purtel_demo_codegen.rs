@@ -1,16 +1,16 @@
 //! purtel demo with code generation of parameter usage.
 
 //! We need rust nightly because of to use procedural macros on expressions.
-//! 1) `#![feature(stmt_expr_attributes)]`
-//! 2) `#![feature(proc_macro_hygiene)]`
-#![feature(stmt_expr_attributes)]
+//! 1) `#![feature(proc_macro_hygiene)]`
+//!
+//! `#[purtel_task]` on closures doesn't need `stmt_expr_attributes` because
+//! `#[purtel_tasks]` removes these attributes before the compiler sees them.
 #![feature(proc_macro_hygiene)]
 
 use std::sync::{Arc, RwLock};
 use std::thread::sleep;
 use std::time::Duration;
-use purtel::{PurtelExecutor, PurtelTask};
-use purtel::{purtel_task, purtel_tasks};
+use purtel::purtel_tasks;
 
 fn main() {
     // All params for the tasks
@@ -18,14 +18,16 @@ fn main() {
     let data2 = Arc::new(RwLock::new(vec![42]));
 
 
-    // this will analyze all purtel task metadata,
-    // UNWRAP(!) the inner block and finally execute everything
-    #[purtel_tasks] {
+    // this will analyze all purtel task metadata, UNWRAP(!) the inner block
+    // and generate the task graph "tasks": all closures and their param usages
+    // in the same order as well as the execution levels
+    #[purtel_tasks(name = "tasks")] {
         // consumes var "data1" read only
         // we move the var into the closure
-        // (and later into a thread)
+        // (and later into a thread);
+        // the usage gets inferred from `data1_t.try_read()`
         let data1_t = data1.clone();
-        #[purtel_task(read = "data1")] {}
+        #[purtel_task]
         let task1 = move || {
             let _data1 = data1_t.try_read().unwrap();
             println!("task 1 is running");
@@ -36,7 +38,7 @@ fn main() {
         let data1_t = data1.clone();
         let data2_t = data2.clone();
         // consumes data1 and data2 read + write
-        #[purtel_task(write = "data1, data2")] {}
+        #[purtel_task(write(data1, data2))]
         let task2 = move || {
             let _data1 = data1_t.try_write().unwrap();
             let _data2 = data2_t.try_write().unwrap();
@@ -45,17 +47,16 @@ fn main() {
         };
 
         let data1_t = data1.clone();
-        // consumes data1 read only
-        #[purtel_task(read = "data1")] {}
-        let task3 = move || {
+        // consumes data1 read only; the attribute can also be attached to the closure
+        let task3 = #[purtel_task(read(data1))] move || {
             let _data1 = data1_t.try_read().unwrap();
             println!("task 3 is running");
             sleep(Duration::from_secs(1));
         };
 
         let data2_t = data2.clone();
-        // consumes data2 read only
-        #[purtel_task(read = "data2")] {}
+        // consumes data2 read only (inferred)
+        #[purtel_task]
         let task4 = move || {
             let _data2 = data2_t.try_read().unwrap();
             println!("task 4 is running");
@@ -63,8 +64,8 @@ fn main() {
         };
 
         let data2_t = data2.clone();
-        // consumes data2 read only
-        #[purtel_task(read = "data2")] {}
+        // consumes data2 read only (inferred)
+        #[purtel_task]
         let task5 = move || {
             let _data2 = data2_t.try_read().unwrap();
             println!("task 5 is running");
@@ -72,24 +73,24 @@ fn main() {
         };
 
         let data2_t = data2.clone();
-        // consumes data2 read only
-        #[purtel_task(read = "data2")] {}
+        // consumes data2 read only (inferred)
+        #[purtel_task]
         let task6 = move || {
             let _data2 = data2_t.try_read().unwrap();
             println!("task 6 is running");
             sleep(Duration::from_secs(1));
         };
         let data2_t = data2.clone();
-        // consumes data2 read only
-        #[purtel_task(read = "data2")] {}
+        // consumes data2 read only (inferred)
+        #[purtel_task]
         let task7 = move || {
             let _data2 = data2_t.try_read().unwrap();
             println!("task 7 is running");
             sleep(Duration::from_secs(1));
         };
         let data2_t = data2.clone();
-        // consumes data2 read only
-        #[purtel_task(read = "data2")] {}
+        // consumes data2 read only (inferred)
+        #[purtel_task]
         let task8 = move || {
             let _data2 = data2_t.try_read().unwrap();
             println!("task 8 is running");
@@ -97,8 +98,8 @@ fn main() {
         };
 
         let data2_t = data2.clone();
-        // consumes data2 read only
-        #[purtel_task(read = "data2")] {}
+        // consumes data2 read only (inferred)
+        #[purtel_task]
         let task9 = move || {
             let _data2 = data2_t.try_read().unwrap();
             println!("task 9 is running");
@@ -106,23 +107,12 @@ fn main() {
         };
     };
 
-    // this can be easily constructed by a regular macro in future
-    let closures = vec![
-        PurtelTask::new(Box::from(task1)),
-        PurtelTask::new(Box::from(task2)),
-        PurtelTask::new(Box::from(task3)),
-        PurtelTask::new(Box::from(task4)),
-        PurtelTask::new(Box::from(task5)),
-        PurtelTask::new(Box::from(task6)),
-        PurtelTask::new(Box::from(task7)),
-        PurtelTask::new(Box::from(task8)),
-        PurtelTask::new(Box::from(task9)),
-    ];
+    // "tasks" got generated by #[purtel_tasks]; the schedule
+    // was calculated at compile time, no runtime planning needed
 
     // Blocking
-    let mut executor = PurtelExecutor::new(closures, param_usages);
-    executor.calc_and_verify_exe_order();
-    executor.execute();
+    let executor = tasks.into_executor();
+    executor.execute().expect("Execution of all tasks must succeed!");
 }
 
 
@@ -124,7 +124,7 @@ fn main() {
     // Blocking
     let mut executor = PurtelExecutor::new(closures, param_usages);
     executor.calc_and_verify_exe_order();
-    executor.execute();
+    executor.execute().expect("Execution of all tasks must succeed!");
 }
 
 
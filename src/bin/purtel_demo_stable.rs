@@ -0,0 +1,72 @@
+//! purtel demo with code generation of parameter usage that works on stable Rust.
+//! Instead of the attribute `#[purtel_tasks]` on a block it uses the function-like
+//! macro `purtel_block!`. With the option `run` the macro also builds and runs the
+//! executor and evaluates to the execution result.
+
+use std::sync::{Arc, RwLock};
+use std::thread::sleep;
+use std::time::Duration;
+use purtel::{purtel_block, purtel_task};
+
+/// A task function: writes `sums`, reads `values`; that follows from the signature.
+/// (`&Vec` instead of a slice because the type must be the data behind the shared handle)
+#[purtel_task]
+#[allow(clippy::ptr_arg)]
+fn append_sum(sums: &mut Vec<i32>, values: &Vec<i32>) {
+    sums.push(values.iter().sum());
+    println!("task 5 is running");
+}
+
+fn main() {
+    // All params for the tasks
+    let data1 = Arc::new(RwLock::new(vec![1, 2, 3, 4, 5]));
+    let data2 = Arc::new(RwLock::new(vec![42]));
+    let sums = Arc::new(RwLock::new(vec![]));
+
+    // one parallel region; evaluates to the result of `PurtelExecutor::execute()`
+    let result = purtel_block! {
+        #![purtel_tasks(run)]
+
+        let data1_t = data1.clone();
+        // consumes data1 read only
+        #[purtel_task(read(data1))]
+        let task1 = move || {
+            let _data1 = data1_t.try_read().unwrap();
+            println!("task 1 is running");
+            sleep(Duration::from_secs(1));
+        };
+
+        // consumes data1 and data2 read + write; with `guards` the macro clones
+        // data1 and data2 into the task and binds their write guards
+        #[purtel_task(write(data1, data2), guards)]
+        let task2 = move || {
+            data1.push(6);
+            data2.push(data1.len());
+            println!("task 2 is running");
+            sleep(Duration::from_secs(1));
+        };
+
+        let data1_t = data1.clone();
+        // consumes data1 read only
+        #[purtel_task(read(data1))]
+        let task3 = move || {
+            let _data1 = data1_t.try_read().unwrap();
+            println!("task 3 is running");
+            sleep(Duration::from_secs(1));
+        };
+
+        // consumes data2 read only; `data2` is the read guard
+        #[purtel_task(read(data2), guards)]
+        let task4 = move || {
+            println!("task 4 is running, data2 = {:?}", *data2);
+            sleep(Duration::from_secs(1));
+        };
+
+        // registers a task that calls `append_sum()` with the guards of sums and data1
+        #[purtel_task] append_sum(sums, data1);
+    };
+
+    let report = result.expect("Execution of all tasks must succeed!");
+    println!("executed levels: {:?}", report.exe_order());
+    println!("sums: {:?}", sums.read().unwrap());
+}
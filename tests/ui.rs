@@ -0,0 +1,11 @@
+//! Compile tests of the purtel macros and of the type-checked APIs. The cases in `ui/pass`
+//! must compile and run without a panic, the cases in `ui/fail` must fail with the errors in
+//! the `.stderr` file next to them. `TRYBUILD=overwrite cargo test --test ui` updates the
+//! `.stderr` files.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,24 @@
+use std::sync::{Arc, Mutex};
+use purtel::purtel_block;
+
+fn main() {
+    let log = Arc::new(Mutex::new(vec![]));
+
+    let log_t = log.clone();
+    let log_tt = log.clone();
+    purtel_block! {
+        #[purtel_task(after = flush)]
+        let report = move || log_t.lock().unwrap().push("report");
+        #[purtel_task]
+        let flush = move || log_tt.lock().unwrap().push("flush");
+    }
+
+    // a cycle
+    purtel_block! {
+        #![purtel_tasks(run)]
+        #[purtel_task(after = task2)]
+        let task1 = || {};
+        #[purtel_task(after = task1)]
+        let task2 = || {};
+    };
+}
@@ -0,0 +1,13 @@
+use std::sync::{Arc, RwLock};
+use purtel::purtel_block;
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    purtel_block! {
+        #![purtel_tasks(name = "tasks", closures = "closures1")]
+        let data1_t = data1.clone();
+        #[purtel_task]
+        let task1 = move || *data1_t.write().unwrap() += 1;
+    }
+}
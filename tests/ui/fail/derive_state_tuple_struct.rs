@@ -0,0 +1,12 @@
+use purtel::PurtelState;
+
+#[derive(PurtelState)]
+struct World(Vec<f64>, Vec<f64>);
+
+#[derive(PurtelState)]
+enum Phase {
+    Init,
+    Run,
+}
+
+fn main() {}
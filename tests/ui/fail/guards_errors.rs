@@ -0,0 +1,21 @@
+use std::sync::{Arc, RwLock};
+use purtel::purtel_block;
+
+struct State {
+    data1: Arc<RwLock<i32>>,
+}
+
+fn main() {
+    let state = State { data1: Arc::new(RwLock::new(0)) };
+
+    purtel_block! {
+        #[purtel_task(write(state.data1), guards)]
+        let task1 = || *data1 += 1;
+    }
+
+    let data1 = state.data1;
+    purtel_block! {
+        #[purtel_task(write(data1[0..2]), guards)]
+        let task1 = || data1[0] += 1;
+    }
+}
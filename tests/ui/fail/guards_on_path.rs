@@ -0,0 +1,8 @@
+use purtel::purtel_block;
+
+fn main() {
+    purtel_block! {
+        #[purtel_task(write(state::data1), guards)]
+        let task1 = || *data1 += 1;
+    }
+}
@@ -0,0 +1,14 @@
+use std::sync::{Arc, RwLock};
+use purtel::{PurtelExecutor, PurtelParamUsage, PurtelParamUsageKind, PurtelShared, PurtelTask};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+    let data2 = Arc::new(PurtelShared::new("data2", 0));
+    let tasks = vec![PurtelTask::new(Box::new(|| {}))];
+    let param_usages = vec![vec![PurtelParamUsage::new("data1", PurtelParamUsageKind::WRITE)]];
+    let mut executor = PurtelExecutor::new(tasks, param_usages);
+
+    // only `PurtelVersioned` keeps the versions that renaming needs
+    executor.rename(&data1).unwrap();
+    executor.rename(&data2).unwrap();
+}
@@ -0,0 +1,12 @@
+use std::sync::{Arc, RwLock};
+use purtel::purtel_block;
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    purtel_block! {
+        let data1_t = data1.clone();
+        #[purtel_task(write = "data1")]
+        let task1 = move || *data1_t.write().unwrap() += 1;
+    }
+}
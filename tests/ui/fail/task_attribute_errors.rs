@@ -0,0 +1,42 @@
+use std::sync::{Arc, RwLock};
+use purtel::purtel_block;
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    // unknown key
+    purtel_block! {
+        #[purtel_task(modify(data1))]
+        move || {};
+    }
+
+    // duplicate key
+    purtel_block! {
+        #[purtel_task(read(data1), read(data1))]
+        move || {};
+    }
+
+    // empty list
+    purtel_block! {
+        #[purtel_task(read())]
+        move || {};
+    }
+
+    // a parameter in both `read` and `write`
+    purtel_block! {
+        #[purtel_task(read(data1), write(data1))]
+        move || {};
+    }
+
+    // duplicate parameter
+    purtel_block! {
+        #[purtel_task(write(data1, data1))]
+        move || {};
+    }
+
+    // invalid range
+    purtel_block! {
+        #[purtel_task(write(data1[4..2]))]
+        move || {};
+    }
+}
@@ -0,0 +1,14 @@
+#![feature(stmt_expr_attributes)]
+#![feature(proc_macro_hygiene)]
+
+use std::sync::{Arc, RwLock};
+use purtel::purtel_tasks;
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    #[purtel_tasks] {
+        #[purtel_task(write(data1))]
+        data1.clone();
+    };
+}
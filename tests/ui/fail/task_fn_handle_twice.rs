@@ -0,0 +1,18 @@
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, purtel_task};
+
+#[purtel_task]
+fn add(to: &mut i32, from: &i32) {
+    *to += *from;
+}
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    purtel_block! {
+        #![purtel_tasks(run)]
+        let data1_t = data1.clone();
+        #[purtel_task]
+        add(data1, data1_t);
+    };
+}
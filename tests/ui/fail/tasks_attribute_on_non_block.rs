@@ -0,0 +1,8 @@
+#![feature(stmt_expr_attributes, proc_macro_hygiene)]
+
+use purtel::purtel_tasks;
+
+fn main() {
+    #[purtel_tasks]
+    let x = 1;
+}
@@ -0,0 +1,19 @@
+use std::sync::{Arc, RwLock};
+use purtel::purtel_block;
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+    let count = 0;
+
+    purtel_block! {
+        #![purtel_tasks(run)]
+        // typo
+        let data1_t = data1.clone();
+        #[purtel_task(write(dta1))]
+        let task1 = move || *data1_t.write().unwrap() += 1;
+
+        // not a shared handle
+        #[purtel_task(read(count))]
+        let task2 = move || println!("{}", count);
+    };
+}
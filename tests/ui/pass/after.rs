@@ -0,0 +1,45 @@
+//! `after(...)` orders tasks that share no data; also after a task later in the block and
+//! also after the executor was re-planned.
+
+use std::sync::{Arc, Mutex};
+use purtel::{purtel_block, PurtelAliases, PurtelExecutionMode};
+
+fn main() {
+    let log = Arc::new(Mutex::new(vec![]));
+
+    let log_t = log.clone();
+    let log_tt = log.clone();
+    let log_ttt = log.clone();
+    purtel_block! {
+        #![purtel_tasks(name = "tasks")]
+        #[purtel_task(after = flush)]
+        let report = move || log_t.lock().unwrap().push("report");
+        #[purtel_task(after(flush, report))]
+        let exit = move || log_tt.lock().unwrap().push("exit");
+        #[purtel_task]
+        let flush = move || log_ttt.lock().unwrap().push("flush");
+    }
+
+    assert_eq!(&[(0, 2), (1, 2), (1, 0)], tasks.dependencies());
+    let mut executor = tasks.into_executor();
+    // replaces the precomputed schedule; the dependencies must survive
+    executor.set_aliases(PurtelAliases::new());
+    executor.set_execution_mode(PurtelExecutionMode::PARALLEL);
+    let report = executor.execute().unwrap();
+    assert_eq!(&[vec![2], vec![0], vec![1]], report.exe_order());
+    assert_eq!(vec!["flush", "report", "exit"], *log.lock().unwrap());
+
+    // the same with `run`
+    log.lock().unwrap().clear();
+    let log_t = log.clone();
+    let log_tt = log.clone();
+    purtel_block! {
+        #![purtel_tasks(run)]
+        #[purtel_task(after = second)]
+        let first = move || log_t.lock().unwrap().push("first");
+        #[purtel_task]
+        let second = move || log_tt.lock().unwrap().push("second");
+    }
+    .unwrap();
+    assert_eq!(vec!["second", "first"], *log.lock().unwrap());
+}
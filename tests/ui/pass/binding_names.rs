@@ -0,0 +1,33 @@
+//! Options for the generated code: the names of the bindings and the path to the crate.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, PurtelExecutor};
+
+// the generated code refers to the purtel types only through this path
+mod renamed {
+    pub use purtel::*;
+}
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+    let data2 = Arc::new(RwLock::new(0));
+
+    // two blocks in the same scope with their own names
+    purtel_block! {
+        #![purtel_tasks(closures = "tasks1", param_usages = "usages1", schedule = "SCHEDULE1")]
+        let data1_t = data1.clone();
+        #[purtel_task]
+        let task1 = move || *data1_t.write().unwrap() += 1;
+    }
+    purtel_block! {
+        #![purtel_tasks(closures = "tasks2", param_usages = "usages2", schedule = "SCHEDULE2", crate = "renamed")]
+        let data2_t = data2.clone();
+        #[purtel_task]
+        let task2 = move || *data2_t.write().unwrap() += 2;
+    }
+
+    PurtelExecutor::with_schedule(tasks1, usages1, &SCHEDULE1).execute().unwrap();
+    PurtelExecutor::with_schedule(tasks2, usages2, &SCHEDULE2).execute().unwrap();
+    assert_eq!(1, *data1.read().unwrap());
+    assert_eq!(2, *data2.read().unwrap());
+}
@@ -0,0 +1,42 @@
+//! The macro generates `closures` and `param_usages` in the same order.
+
+use std::sync::{Arc, Mutex, RwLock};
+use purtel::{purtel_block, PurtelExecutionMode, PurtelExecutor, PurtelParamUsageKind};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+    let data2 = Arc::new(RwLock::new(0));
+    let log = Arc::new(Mutex::new(vec![]));
+
+    purtel_block! {
+        let data1_t = data1.clone();
+        let log_t = log.clone();
+        #[purtel_task(write(data1))]
+        let task1 = move || {
+            *data1_t.write().unwrap() += 1;
+            log_t.lock().unwrap().push(1);
+        };
+
+        let data2_t = data2.clone();
+        let log_t = log.clone();
+        #[purtel_task(read(data2))]
+        let task2 = move || {
+            let _data2 = data2_t.read().unwrap();
+            log_t.lock().unwrap().push(2);
+        };
+    }
+
+    assert_eq!(2, closures.len());
+    assert_eq!(2, param_usages.len());
+    assert_eq!("data1", param_usages[0][0].identifier());
+    assert_eq!(PurtelParamUsageKind::WRITE, param_usages[0][0].kind());
+    assert_eq!("data2", param_usages[1][0].identifier());
+    assert_eq!(PurtelParamUsageKind::READ, param_usages[1][0].kind());
+
+    let mut executor = PurtelExecutor::new(closures, param_usages);
+    executor.set_execution_mode(PurtelExecutionMode::SEQUENTIAL_BY_ID);
+    executor.calc_and_verify_exe_order();
+    executor.execute().unwrap();
+    assert_eq!(vec![1, 2], *log.lock().unwrap());
+    assert_eq!(1, *data1.read().unwrap());
+}
@@ -0,0 +1,48 @@
+//! A declared clone counts as the parameter it was cloned from, like an inferred one;
+//! tasks that declare and tasks that infer the same data depend on each other.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, PurtelExecutor, PurtelParamUsageKind, PurtelStaticSchedule};
+
+fn increment(data: &RwLock<i32>) {
+    *data.write().unwrap() += 1;
+}
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    purtel_block! {
+        // the access inside the function can't be inferred
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1_t))]
+        let declared = move || increment(&data1_t);
+
+        let data1_t = data1.clone();
+        #[purtel_task]
+        let inferred = move || {
+            let value = *data1_t.read().unwrap();
+            assert_eq!(1, value);
+        };
+
+        // the declaration overrides the inferred WRITE of the same parameter
+        let data1_t = Arc::clone(&data1);
+        #[purtel_task(read(data1_t))]
+        let overridden = move || {
+            let _guard = data1_t.write().unwrap();
+        };
+    }
+
+    let kinds = param_usages.iter()
+        .map(|usages| usages.iter().map(|usage| (usage.identifier().to_owned(), usage.kind())).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![("data1".to_owned(), PurtelParamUsageKind::WRITE)], kinds[0]);
+    assert_eq!(vec![("data1".to_owned(), PurtelParamUsageKind::READ)], kinds[1]);
+    assert_eq!(vec![("data1".to_owned(), PurtelParamUsageKind::READ)], kinds[2]);
+
+    const SCHEDULE: PurtelStaticSchedule = PURTEL_SCHEDULE;
+    let task_deps: &[&[usize]] = &[&[], &[0], &[0]];
+    assert_eq!(task_deps, SCHEDULE.task_deps());
+
+    PurtelExecutor::with_schedule(closures, param_usages, &SCHEDULE).execute().unwrap();
+    assert_eq!(1, *data1.read().unwrap());
+}
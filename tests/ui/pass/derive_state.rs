@@ -0,0 +1,55 @@
+//! `#[derive(PurtelState)]`: every field is an independent parameter, hence tasks on
+//! different fields don't depend on each other.
+
+use purtel::{purtel_block, PurtelState};
+
+#[derive(PurtelState)]
+struct World {
+    positions: Vec<f64>,
+    velocities: Vec<f64>,
+    steps: u32,
+}
+
+fn main() {
+    let world = World { positions: vec![0.0], velocities: vec![2.0], steps: 0 };
+    let state = world.into_shared("state");
+
+    purtel_block! {
+        #![purtel_tasks(name = "tasks")]
+        let state_t = state.clone();
+        #[purtel_task]
+        let integrate = move || {
+            let velocities = state_t.velocities.read();
+            state_t.positions.write()[0] += velocities[0];
+        };
+
+        let state_t = state.clone();
+        #[purtel_task]
+        let count = move || *state_t.steps.write() += 1;
+    }
+
+    let identifiers = tasks.param_usages().iter()
+        .map(|usages| usages.iter().map(|usage| usage.identifier()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![vec!["state.positions", "state.velocities"], vec!["state.steps"]], identifiers);
+    let executor = tasks.into_executor();
+    executor.execute().unwrap();
+
+    let world = state.into_inner();
+    assert_eq!(vec![2.0], world.positions);
+    assert_eq!(1, world.steps);
+
+    // the name doesn't match the variable: the tasks would declare `state.steps` but
+    // access `world.steps`
+    let state = world.into_shared("world");
+    let result = std::panic::catch_unwind(move || {
+        purtel_block! {
+            let state_t = state.clone();
+            #[purtel_task(write(state_t.steps))]
+            let count = move || *state_t.steps.write() += 1;
+        }
+    });
+    let err = result.unwrap_err();
+    let message = err.downcast_ref::<String>().unwrap();
+    assert!(message.contains("The declared parameter 'state.steps' is the data 'world.steps'"), "{}", message);
+}
@@ -0,0 +1,24 @@
+//! With `guards` the macro clones the handles into the task and binds their guards.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, PurtelReduction};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(vec![1, 2, 3]));
+    let data2 = Arc::new(RwLock::new(vec![]));
+    let sum = Arc::new(PurtelReduction::new("sum", 0, || 0, |acc: &mut i32, x| *acc += x));
+
+    purtel_block! {
+        #![purtel_tasks(run)]
+        #[purtel_task(read(data1), write(data2), guards)]
+        let task1 = || data2.extend(data1.iter().map(|x| x * 10));
+
+        #[purtel_task(read(data1), reduce(sum), guards)]
+        let task2 = || sum.reduce(|local| *local += data1.iter().sum::<i32>());
+    }
+    .unwrap();
+
+    // the handles are still usable after the block
+    assert_eq!(vec![10, 20, 30], *data2.read().unwrap());
+    assert_eq!(6, *sum.read());
+}
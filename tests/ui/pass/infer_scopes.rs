@@ -0,0 +1,64 @@
+//! A variable declared in the closure hides a captured variable only from its declaration
+//! to the end of its block.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, PurtelExecutor, PurtelParamUsageKind};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+    let data2 = Arc::new(RwLock::new(0));
+    let data3 = Arc::new(RwLock::new(0));
+
+    purtel_block! {
+        // shadowing: the initializer accesses the captured `d`
+        let d = data1.clone();
+        #[purtel_task]
+        let shadowing = move || {
+            let mut d = d.write().unwrap();
+            *d += 1;
+        };
+
+        // nested block: the local `data2_t` ends with the inner block
+        let data2_t = data2.clone();
+        #[purtel_task]
+        let nested_block = move || {
+            {
+                let data2_t = RwLock::new(0);
+                *data2_t.write().unwrap() += 1;
+            }
+            let _value = *data2_t.read().unwrap();
+        };
+
+        // later `let`: the access happens before the local `data3_t` is declared
+        let data3_t = data3.clone();
+        #[purtel_task]
+        let later_let = move || {
+            *data3_t.write().unwrap() += 1;
+            let data3_t = RwLock::new(0);
+            let _value = *data3_t.read().unwrap();
+        };
+
+        // the local really hides the captured variable
+        let data3_t = data3.clone();
+        #[purtel_task]
+        let local = move || {
+            let _value = *data3_t.read().unwrap();
+            let data3_t = RwLock::new(0);
+            *data3_t.write().unwrap() += 1;
+        };
+    }
+
+    let kinds = |task: usize| {
+        param_usages[task].iter().map(|usage| (usage.identifier(), usage.kind())).collect::<Vec<_>>()
+    };
+    assert_eq!(vec![("data1", PurtelParamUsageKind::WRITE)], kinds(0));
+    assert_eq!(vec![("data2", PurtelParamUsageKind::READ)], kinds(1));
+    assert_eq!(vec![("data3", PurtelParamUsageKind::WRITE)], kinds(2));
+    assert_eq!(vec![("data3", PurtelParamUsageKind::READ)], kinds(3));
+
+    let mut executor = PurtelExecutor::new(closures, param_usages);
+    executor.calc_and_verify_exe_order();
+    executor.execute().unwrap();
+    assert_eq!(1, *data1.read().unwrap());
+    assert_eq!(1, *data3.read().unwrap());
+}
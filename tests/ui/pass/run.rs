@@ -0,0 +1,25 @@
+//! With `run` the block builds, plans and executes the tasks and evaluates to the result.
+
+use std::sync::{Arc, RwLock};
+use purtel::purtel_block;
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(1));
+    let data2 = Arc::new(RwLock::new(0));
+
+    let result = purtel_block! {
+        #![purtel_tasks(run)]
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1))]
+        let task1 = move || *data1_t.write().unwrap() *= 10;
+
+        let data1_t = data1.clone();
+        let data2_t = data2.clone();
+        #[purtel_task(read(data1), write(data2))]
+        let task2 = move || *data2_t.write().unwrap() = *data1_t.read().unwrap() + 1;
+    };
+
+    result.unwrap();
+    assert_eq!(10, *data1.read().unwrap());
+    assert_eq!(11, *data2.read().unwrap());
+}
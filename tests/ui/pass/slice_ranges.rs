@@ -0,0 +1,26 @@
+//! Tasks on disjoint ranges of a `PurtelSlice` run in the same execution level.
+
+use std::sync::Arc;
+use purtel::{purtel_block, PurtelSlice};
+
+fn main() {
+    let data1 = Arc::new(PurtelSlice::new("data1", vec![0; 8]));
+
+    purtel_block! {
+        #![purtel_tasks(name = "tasks")]
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1[0..4]))]
+        let chunk1 = move || data1_t.write(0..4).fill(1);
+
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1[4..8]))]
+        let chunk2 = move || data1_t.write(4..8).fill(2);
+
+        let data1_t = data1.clone();
+        #[purtel_task(read(data1))]
+        let sum = move || assert_eq!(12, data1_t.read(0..8).iter().sum::<i32>());
+    }
+
+    let report = tasks.into_executor().execute().unwrap();
+    assert_eq!(&[vec![0, 1], vec![2]], report.exe_order());
+}
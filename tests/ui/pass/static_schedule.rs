@@ -0,0 +1,35 @@
+//! The macro computes the dependencies and execution levels at compile time.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, PurtelExecutor, PurtelStaticSchedule};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(1));
+    let data2 = Arc::new(RwLock::new(2));
+
+    purtel_block! {
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1))]
+        let task1 = move || *data1_t.write().unwrap() += 1;
+
+        let data2_t = data2.clone();
+        #[purtel_task(write(data2))]
+        let task2 = move || *data2_t.write().unwrap() += 1;
+
+        let data1_t = data1.clone();
+        let data2_t = data2.clone();
+        #[purtel_task(read(data2), write(data1))]
+        let task3 = move || *data1_t.write().unwrap() += *data2_t.read().unwrap();
+    }
+
+    // usable in const context
+    const SCHEDULE: PurtelStaticSchedule = PURTEL_SCHEDULE;
+    let task_deps: &[&[usize]] = &[&[], &[], &[0, 1]];
+    assert_eq!(task_deps, SCHEDULE.task_deps());
+    let exe_order: &[&[usize]] = &[&[0, 1], &[2]];
+    assert_eq!(exe_order, SCHEDULE.exe_order());
+
+    let executor = PurtelExecutor::with_schedule(closures, param_usages, &SCHEDULE);
+    executor.execute().unwrap();
+    assert_eq!(5, *data1.read().unwrap());
+}
@@ -0,0 +1,30 @@
+//! `#[purtel_task]` on a `let` binding, on the closure itself and on an anonymous closure.
+#![feature(stmt_expr_attributes)]
+#![feature(proc_macro_hygiene)]
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_task, purtel_tasks, PurtelExecutor};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(0));
+
+    #[purtel_tasks] {
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1))]
+        let task1 = move || *data1_t.write().unwrap() += 1;
+
+        let data1_t = data1.clone();
+        let task2 = #[purtel_task(write(data1))] move || *data1_t.write().unwrap() += 1;
+
+        let data1_t = data1.clone();
+        #[purtel_task(write(data1))]
+        move || *data1_t.write().unwrap() += 1;
+    };
+
+    assert_eq!(3, closures.len());
+    assert!(param_usages.iter().all(|usages| usages[0].identifier() == "data1"));
+    let mut executor = PurtelExecutor::new(closures, param_usages);
+    executor.calc_and_verify_exe_order();
+    executor.execute().unwrap();
+    assert_eq!(3, *data1.read().unwrap());
+}
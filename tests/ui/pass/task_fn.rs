@@ -0,0 +1,32 @@
+//! `#[purtel_task]` on a function: `&mut` parameters are WRITE, `&` parameters are READ,
+//! all other parameters are passed by value and may have the same value.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, purtel_task, PurtelParamUsageKind};
+
+#[purtel_task]
+fn integrate(pos: &mut Vec<f64>, vel: &Vec<f64>, dt: f64, damping: f64) {
+    for (p, v) in pos.iter_mut().zip(vel) {
+        *p += v * dt * (1.0 - damping);
+    }
+}
+
+fn main() {
+    let pos = Arc::new(RwLock::new(vec![0.0, 1.0]));
+    let vel = Arc::new(RwLock::new(vec![10.0, 20.0]));
+
+    purtel_block! {
+        #![purtel_tasks(name = "tasks")]
+        let pos_t = pos.clone();
+        #[purtel_task]
+        integrate(pos_t, vel, 0.5, 0.5);
+    }
+
+    let usages = tasks.param_usages()[0].iter()
+        .map(|usage| (usage.identifier(), usage.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![("pos", PurtelParamUsageKind::WRITE), ("vel", PurtelParamUsageKind::READ)], usages);
+
+    tasks.into_executor().execute().unwrap();
+    assert_eq!(vec![2.5, 6.0], *pos.read().unwrap());
+}
@@ -0,0 +1,35 @@
+//! `read(...)`/`write(...)` take identifiers with exact set semantics: writing `data10`
+//! doesn't hide the read of `data1`.
+
+use std::sync::{Arc, RwLock};
+use purtel::{purtel_block, PurtelExecutor, PurtelParamUsageKind};
+
+fn main() {
+    let data1 = Arc::new(RwLock::new(1));
+    let data10 = Arc::new(RwLock::new(0));
+    let data2 = Arc::new(RwLock::new(0));
+
+    purtel_block! {
+        let data1_t = data1.clone();
+        let data10_t = data10.clone();
+        let data2_t = data2.clone();
+        #[purtel_task(read(data1), write(data10, data2))]
+        let task1 = move || {
+            *data10_t.write().unwrap() = *data1_t.read().unwrap() * 10;
+            *data2_t.write().unwrap() = 2;
+        };
+    }
+
+    let usages = param_usages[0].iter()
+        .map(|usage| (usage.identifier(), usage.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(3, usages.len());
+    assert!(usages.contains(&("data1", PurtelParamUsageKind::READ)));
+    assert!(usages.contains(&("data10", PurtelParamUsageKind::WRITE)));
+    assert!(usages.contains(&("data2", PurtelParamUsageKind::WRITE)));
+
+    let mut executor = PurtelExecutor::new(closures, param_usages);
+    executor.calc_and_verify_exe_order();
+    executor.execute().unwrap();
+    assert_eq!(10, *data10.read().unwrap());
+}